@@ -1,7 +1,8 @@
 //! [Asset] represent a FSO Datasource
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::tools::downloader::CacheInfo;
 use crate::tools::Downloader;
 
 /// Type for id of FSO asset
@@ -34,7 +35,32 @@ impl Asset {
     where
         D: Downloader,
     {
-        downloader.cache_get(&self.url_data())
+        let path = downloader.cache_get(&self.url_data())?;
+        self.record(&path)?;
+        Ok(path)
+    }
+
+    /// Download data in a file and return the path of file, the file must
+    /// have the SHA-256 `sha256` (hexadecimal) if given
+    pub fn data_file_verified<D>(
+        &self,
+        downloader: D,
+        sha256: Option<&str>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>>
+    where
+        D: Downloader,
+    {
+        let path = downloader.cache_get_verified(&self.url_data(), sha256)?;
+        self.record(&path)?;
+        Ok(path)
+    }
+
+    /// Record the asset id in information of cached file
+    fn record(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if CacheInfo::load(path).is_some_and(|info| info.asset == Some(self.0)) {
+            return Ok(());
+        }
+        CacheInfo::update(path, |info| info.asset = Some(self.0))
     }
 
     /// Download bibtex
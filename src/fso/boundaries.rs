@@ -0,0 +1,568 @@
+//! Generalised boundaries of municipalities, linked to [MunicipalityId]
+//!
+//! - Data source (**FSO**: `ag-b-00.03-875-gg`)
+//!   - Generalisierte Gemeindegrenzen: Geodaten
+//!   - Limites communales généralisées: géodonnées
+//!   - Confini comunali generalizzati: geodati
+//!   - [Terms of use 'OPEN-BY-ASK'][terms]
+//!
+//! A release is published every year, each release is an asset (zip with
+//! shapefiles), see the asset number on the FSO webpage. Only the
+//! municipality layer with the most detailed generalisation (`g1g*`) is
+//! loaded, on LV95 coordinates (LV03 coordinates of older releases are
+//! translated). See [releases] for select the release of a date.
+//!
+//! [Boundaries::locate] resolve a coordinate (LV95, LV03 or WGS84, see
+//! [crate::tools::geo]) to a municipality with a grid spatial index.
+//!
+//! ```no_run
+//! use swissdata::fso::boundaries;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::tools::geo::Wgs84;
+//!
+//! # let asset = 0;
+//! // asset number of the release, see FSO webpage
+//! let store = boundaries::datastore(asset);
+//! let boundaries = store.load(&reqwest::blocking::Client::new())?;
+//! let point = Wgs84 {
+//!     latitude: 46.948,
+//!     longitude: 7.447,
+//! };
+//! if let Some(boundary) = boundaries.locate(point) {
+//!     println!("{} {}", boundary.id, boundary.name);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [terms]: https://www.bfs.admin.ch/bfs/en/home/fso/swiss-federal-statistical-office/terms-of-use.html
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error;
+use std::fs::File;
+use std::io::Read;
+use std::slice;
+
+use encoding_rs::{UTF_8, WINDOWS_1252};
+use zip::ZipArchive;
+
+use crate::fso::asset::{Asset, AssetId};
+use crate::fso::communes::MunicipalityId;
+use crate::tools::geo::{Lv03, Lv95};
+use crate::tools::internal::shapefile;
+use crate::tools::Downloader;
+use crate::tools::{dataset, meta};
+
+pub mod releases;
+
+/// Prefix of shapefile for municipalities (generalisation level 1)
+const LAYER_PREFIX: &str = "g1g";
+/// Field of the municipality number
+const ID_FIELD: &str = "GMDNR";
+/// Field of the municipality name
+const NAME_FIELD: &str = "GMDNAME";
+
+/// Get the generalised boundaries datastore for a release (FSO asset)
+pub fn datastore(asset: AssetId) -> Datastore {
+    Datastore {
+        asset,
+        sha256: None,
+    }
+}
+
+/// Load the municipality layer of a generalised boundaries release
+pub struct Datastore {
+    asset: AssetId,
+    sha256: Option<String>,
+}
+impl Datastore {
+    /// Pin the SHA-256 (hexadecimal) of zip file, [load] fail if the file
+    /// doesn't match (for reproducible runs)
+    ///
+    /// [load]: dataset::Datastore::load
+    pub fn with_sha256<S: Into<String>>(mut self, sha256: S) -> Self {
+        self.sha256 = Some(sha256.into());
+        self
+    }
+
+    /// Get asset of release (shapefiles in a zip)
+    pub fn asset(&self) -> Asset {
+        self.asset.into()
+    }
+}
+impl dataset::Datastore<&'static str> for Datastore {
+    type Store = Boundaries;
+
+    fn meta(&self) -> meta::Meta<&'static str> {
+        meta::Meta {
+            name: [
+                ("de", "Generalisierte Gemeindegrenzen: Geodaten"),
+                ("fr", "Limites communales généralisées: géodonnées"),
+                ("it", "Confini comunali generalizzati: geodati"),
+            ]
+            .into_iter()
+            .collect(),
+            lang: None,
+            editor: super::editor(),
+            copyright: super::copyright(),
+            terms: super::terms("OPEN-BY-ASK"),
+            terms_automatic: meta::Terms {
+                free_commercial_use: false,
+                free_noncommercial_use: true,
+                citation_mandatory: true,
+            },
+            // depends on release, see Asset::bibtex
+            citations: Default::default(),
+        }
+    }
+
+    fn urls(&self) -> Vec<String> {
+        vec![self.asset().url_data()]
+    }
+
+    fn load<D>(&self, downloader: D) -> Result<Self::Store, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        let path = self
+            .asset()
+            .data_file_verified(downloader, self.sha256.as_deref())?;
+        let mut zip = ZipArchive::new(File::open(path)?)?;
+        // older releases have only LV03 coordinates
+        let layer = |frame: &str| {
+            zip.file_names()
+                .find(|name| {
+                    let name = name.to_lowercase();
+                    let file = name.rsplit('/').next().unwrap_or_default();
+                    name.contains(frame) && file.starts_with(LAYER_PREFIX) && file.ends_with(".shp")
+                })
+                .map(str::to_string)
+        };
+        let (shp, lv03) = match (layer("lv95"), layer("lv03")) {
+            (Some(shp), _) => (shp, false),
+            (None, Some(shp)) => (shp, true),
+            (None, None) => return Err("Missing municipality layer in archive".into()),
+        };
+        let stem = &shp[..shp.len() - ".shp".len()];
+
+        fn read(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+            let mut buffer = Vec::new();
+            zip.by_name(name)?.read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+        let mut shapes = shapefile::read_polygons(&read(&mut zip, &shp)?)?;
+        if lv03 {
+            for point in shapes.iter_mut().flatten().flatten() {
+                let lv95 = Lv95::from(Lv03::from(*point));
+                *point = [lv95.east, lv95.north];
+            }
+        }
+        let utf8 = read(&mut zip, &format!("{stem}.cpg"))
+            .map(|cpg| {
+                String::from_utf8_lossy(&cpg)
+                    .trim()
+                    .eq_ignore_ascii_case("UTF-8")
+            })
+            .unwrap_or(false);
+        let encoding = if utf8 { UTF_8 } else { WINDOWS_1252 };
+        let records = shapefile::read_dbf(&read(&mut zip, &format!("{stem}.dbf"))?, encoding)?;
+        if shapes.len() != records.len() {
+            return Err("Shapes and records of municipality layer don't match".into());
+        }
+
+        shapes
+            .into_iter()
+            .zip(records)
+            .filter_map(|(rings, record)| Some((rings, record?)))
+            .map(|(rings, mut record)| -> Result<_, Box<dyn error::Error>> {
+                let id = record.get(ID_FIELD).ok_or("Missing municipality number")?;
+                Ok(Boundary {
+                    id: id.parse()?,
+                    name: record.remove(NAME_FIELD).unwrap_or_default(),
+                    geometry: Geometry::new(rings),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Polygons of a boundary, on LV95 coordinates (east, north)
+///
+/// Outer rings and holes aren't distinguished, a point is inside if it is
+/// inside an odd number of rings.
+#[derive(Clone, Debug)]
+pub struct Geometry {
+    rings: Vec<Vec<[f64; 2]>>,
+    bbox: [[f64; 2]; 2],
+}
+impl Geometry {
+    /// Build a geometry with rings (closed or not)
+    pub fn new(rings: Vec<Vec<[f64; 2]>>) -> Self {
+        let mut bbox = [[f64::INFINITY; 2], [f64::NEG_INFINITY; 2]];
+        for [x, y] in rings.iter().flatten() {
+            bbox = [
+                [bbox[0][0].min(*x), bbox[0][1].min(*y)],
+                [bbox[1][0].max(*x), bbox[1][1].max(*y)],
+            ];
+        }
+        Self { rings, bbox }
+    }
+
+    /// Rings of polygons
+    pub fn rings(&self) -> &[Vec<[f64; 2]>] {
+        &self.rings
+    }
+
+    /// Bounding box (minimum and maximum coordinates)
+    pub fn bbox(&self) -> [[f64; 2]; 2] {
+        self.bbox
+    }
+
+    /// Check if point is inside geometry
+    pub fn contains(&self, point: Lv95) -> bool {
+        let Lv95 { east: x, north: y } = point;
+        let [[min_x, min_y], [max_x, max_y]] = self.bbox;
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return false;
+        }
+        let mut inside = false;
+        for ring in &self.rings {
+            for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                if (a[1] > y) != (b[1] > y) && x < (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]) + a[0]
+                {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+/// Boundary of a municipality
+#[derive(Clone, Debug)]
+pub struct Boundary {
+    /// BFS number of municipality
+    pub id: MunicipalityId,
+    /// Name of municipality
+    pub name: String,
+    /// Polygons of municipality
+    pub geometry: Geometry,
+}
+
+/// Municipalities sharing a border, by municipality
+pub type Adjacency = BTreeMap<MunicipalityId, BTreeSet<MunicipalityId>>;
+
+/// Uniform grid of boundaries, each cell contains boundaries with a bounding
+/// box overlapping the cell
+struct Grid {
+    origin: [f64; 2],
+    size: f64,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+impl Grid {
+    /// Cells by boundary (on average)
+    const DENSITY: f64 = 4.;
+
+    fn new(boundaries: &[Boundary]) -> Self {
+        let bboxes: Vec<_> = boundaries
+            .iter()
+            .map(|boundary| boundary.geometry.bbox())
+            .filter(|[min, max]| min[0] <= max[0] && min[1] <= max[1])
+            .collect();
+        let origin = bboxes.iter().fold([f64::INFINITY; 2], |acc, [min, _]| {
+            [acc[0].min(min[0]), acc[1].min(min[1])]
+        });
+        let end = bboxes.iter().fold([f64::NEG_INFINITY; 2], |acc, [_, max]| {
+            [acc[0].max(max[0]), acc[1].max(max[1])]
+        });
+        let mut grid = Self {
+            origin,
+            size: 1.,
+            columns: 0,
+            rows: 0,
+            cells: Vec::new(),
+        };
+        if bboxes.is_empty() {
+            return grid;
+        }
+        let (width, height) = (end[0] - origin[0], end[1] - origin[1]);
+        let cells = Self::DENSITY * bboxes.len() as f64;
+        // a flat extent has no area, the number of cells along the larger
+        // dimension is limited
+        grid.size = ((width * height) / cells)
+            .sqrt()
+            .max(width.max(height) / cells)
+            .max(f64::MIN_POSITIVE);
+        grid.columns = (width / grid.size) as usize + 1;
+        grid.rows = (height / grid.size) as usize + 1;
+        grid.cells = vec![Vec::new(); grid.columns * grid.rows];
+        for (i, boundary) in boundaries.iter().enumerate() {
+            let [min, max] = boundary.geometry.bbox();
+            if let (Some(min), Some(max)) = (grid.cell(min), grid.cell(max)) {
+                for row in min.1..=max.1 {
+                    for column in min.0..=max.0 {
+                        grid.cells[row * grid.columns + column].push(i);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// Column and row of the cell containing point
+    fn cell(&self, point: [f64; 2]) -> Option<(usize, usize)> {
+        let column = (point[0] - self.origin[0]) / self.size;
+        let row = (point[1] - self.origin[1]) / self.size;
+        if column < 0. || row < 0. {
+            return None;
+        }
+        let (column, row) = (column as usize, row as usize);
+        (column < self.columns && row < self.rows).then_some((column, row))
+    }
+
+    /// Boundaries can contain point
+    fn candidates(&self, point: Lv95) -> &[usize] {
+        match self.cell([point.east, point.north]) {
+            Some((column, row)) => &self.cells[row * self.columns + column],
+            None => &[],
+        }
+    }
+}
+
+/// Boundaries of municipalities with a spatial index
+pub struct Boundaries {
+    boundaries: Vec<Boundary>,
+    ids: HashMap<MunicipalityId, usize>,
+    grid: Grid,
+}
+impl Boundaries {
+    /// Number of boundaries
+    pub fn len(&self) -> usize {
+        self.boundaries.len()
+    }
+
+    /// Check if there is no boundary
+    pub fn is_empty(&self) -> bool {
+        self.boundaries.is_empty()
+    }
+
+    /// Iterate on boundaries
+    pub fn iter(&self) -> slice::Iter<'_, Boundary> {
+        self.boundaries.iter()
+    }
+
+    /// Get boundary of a municipality
+    pub fn get(&self, id: MunicipalityId) -> Option<&Boundary> {
+        self.ids.get(&id).map(|&i| &self.boundaries[i])
+    }
+
+    /// Get boundary containing a coordinate
+    ///
+    /// On a shared border, one of municipalities is returned.
+    pub fn locate<P: Into<Lv95>>(&self, point: P) -> Option<&Boundary> {
+        let point = point.into();
+        self.grid
+            .candidates(point)
+            .iter()
+            .map(|&i| &self.boundaries[i])
+            .find(|boundary| boundary.geometry.contains(point))
+    }
+
+    /// Graph of municipalities sharing a border
+    ///
+    /// Two municipalities are adjacent if their rings share a segment (shared
+    /// borders of generalised boundaries have the same vertices), coordinates
+    /// are compared to the centimeter. A contact on a single point isn't an
+    /// adjacency.
+    pub fn adjacency(&self) -> Adjacency {
+        let key = |[x, y]: &[f64; 2]| [(x * 100.).round() as i64, (y * 100.).round() as i64];
+        let mut adjacency: Adjacency = self
+            .boundaries
+            .iter()
+            .map(|boundary| (boundary.id, BTreeSet::new()))
+            .collect();
+        let mut segments = HashMap::new();
+        for boundary in &self.boundaries {
+            for ring in boundary.geometry.rings() {
+                for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                    let (a, b) = (key(a), key(b));
+                    if a == b {
+                        continue;
+                    }
+                    match segments.entry(if a < b { [a, b] } else { [b, a] }) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(boundary.id);
+                        }
+                        Entry::Occupied(entry) if *entry.get() != boundary.id => {
+                            let other = *entry.get();
+                            adjacency.entry(boundary.id).or_default().insert(other);
+                            adjacency.entry(other).or_default().insert(boundary.id);
+                        }
+                        Entry::Occupied(_) => {}
+                    }
+                }
+            }
+        }
+        adjacency
+    }
+}
+impl FromIterator<Boundary> for Boundaries {
+    fn from_iter<I: IntoIterator<Item = Boundary>>(iter: I) -> Self {
+        let boundaries: Vec<_> = iter.into_iter().collect();
+        let ids = boundaries
+            .iter()
+            .enumerate()
+            .map(|(i, boundary)| (boundary.id, i))
+            .collect();
+        let grid = Grid::new(&boundaries);
+        Self {
+            boundaries,
+            ids,
+            grid,
+        }
+    }
+}
+impl<'a> IntoIterator for &'a Boundaries {
+    type IntoIter = slice::Iter<'a, Boundary>;
+    type Item = &'a Boundary;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::dataset::Datastore as _;
+    use crate::tools::downloader::mock::Mock;
+    use crate::tools::internal::testing::{dbf, shp, zip};
+
+    fn rectangle(min: [f64; 2], max: [f64; 2]) -> Vec<[f64; 2]> {
+        vec![min, [min[0], max[1]], max, [max[0], min[1]], min]
+    }
+
+    fn boundary(id: u16, rings: Vec<Vec<[f64; 2]>>) -> Boundary {
+        Boundary {
+            id: MunicipalityId::new(id).unwrap(),
+            name: id.to_string(),
+            geometry: Geometry::new(rings),
+        }
+    }
+
+    fn id(boundary: Option<&Boundary>) -> Option<u16> {
+        boundary.map(|boundary| boundary.id.get())
+    }
+
+    /// Two squares sharing a border (the first with a hole) and a square
+    /// touching the second on a corner
+    fn boundaries() -> Boundaries {
+        [
+            boundary(
+                1,
+                vec![
+                    rectangle([0., 0.], [10., 10.]),
+                    rectangle([2., 2.], [4., 4.]),
+                ],
+            ),
+            boundary(2, vec![rectangle([10., 0.], [20., 10.])]),
+            boundary(3, vec![rectangle([20., 10.], [30., 20.])]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn locate() {
+        let boundaries = boundaries();
+        let at = |east, north| id(boundaries.locate(Lv95 { east, north }));
+        assert_eq!(at(5., 5.), Some(1));
+        assert_eq!(at(3., 3.), None);
+        assert_eq!(at(15., 5.), Some(2));
+        assert_eq!(at(25., 15.), Some(3));
+        assert_eq!(at(50., 50.), None);
+        assert_eq!(at(25., 5.), None);
+        // on the shared border
+        assert!(matches!(at(10., 5.), Some(1 | 2)));
+    }
+
+    #[test]
+    fn adjacency() {
+        let adjacency = boundaries().adjacency();
+        let neighbours = |id| {
+            adjacency[&MunicipalityId::new(id).unwrap()]
+                .iter()
+                .map(|id| id.get())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(neighbours(1), [2]);
+        assert_eq!(neighbours(2), [1]);
+        // a contact on a corner isn't an adjacency
+        assert!(neighbours(3).is_empty());
+    }
+
+    #[test]
+    fn flat_extent() {
+        let boundaries: Boundaries = [
+            boundary(1, vec![vec![[0., 0.], [10., 0.]]]),
+            boundary(2, vec![]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            id(boundaries.locate(Lv95 {
+                east: 5.,
+                north: 0.
+            })),
+            None
+        );
+        assert_eq!(boundaries.len(), 2);
+    }
+
+    #[test]
+    fn load() {
+        let store = datastore(0);
+        let shapes = shp(&[
+            Some(vec![rectangle([0., 0.], [10., 10.])]),
+            Some(vec![rectangle([10., 0.], [20., 10.])]),
+            None,
+        ]);
+        let records = dbf(
+            &[(ID_FIELD, 4), (NAME_FIELD, 12)],
+            &[
+                Some(vec![b"6458", b"Neuch\xe2tel"]),
+                None,
+                Some(vec![b"261", b"Z\xfcrich"]),
+            ],
+        );
+        let content = zip([
+            ("ggg_2024_LV95/shp/g1g24.shp".into(), shapes),
+            ("ggg_2024_LV95/shp/g1g24.dbf".into(), records),
+        ]);
+        let mock = Mock::new().with_content(store.asset().url_data(), content);
+
+        let boundaries = store.load(&mock).unwrap();
+        // deleted record is skipped, null shape has no polygon
+        assert_eq!(boundaries.len(), 2);
+        let neuchatel = boundaries.get(MunicipalityId::new(6458).unwrap()).unwrap();
+        assert_eq!(neuchatel.name, "Neuchâtel");
+        assert_eq!(
+            id(boundaries.locate(Lv95 {
+                east: 5.,
+                north: 5.
+            })),
+            Some(6458)
+        );
+        assert_eq!(
+            id(boundaries.locate(Lv95 {
+                east: 15.,
+                north: 5.
+            })),
+            None
+        );
+        let zurich = boundaries.get(MunicipalityId::new(261).unwrap()).unwrap();
+        assert!(zurich.geometry.rings().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+//! Yearly releases of generalised boundaries, aligned with commune snapshots
+//!
+//! Each release has the boundaries of municipalities on 1 January of its
+//! year. [Releases] select the release for a date and [Boundaries::check]
+//! compare the boundaries with the municipalities valid at this date (see
+//! [crate::fso::communes]), municipalities can change during the year.
+//!
+//! ```no_run
+//! use swissdata::fso::boundaries::releases::Releases;
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::Date;
+//!
+//! let client = reqwest::blocking::Client::new();
+//! let communes = communes::datastore().load(&client)?;
+//! # let (asset_2000, asset_2010) = (0, 0);
+//! // asset numbers of releases, see FSO webpage
+//! let releases: Releases = [(2000, asset_2000), (2010, asset_2010)]
+//!     .into_iter()
+//!     .collect();
+//! let date = Date::from_ymd_opt(2005, 6, 1).unwrap();
+//! let snapshot = releases.load_at(&client, &communes.municipalities, date)?;
+//! assert_eq!(snapshot.release, Date::from_ymd_opt(2000, 1, 1).unwrap());
+//! if !snapshot.check.is_consistent() {
+//!     println!("{}", snapshot.check);
+//! }
+//! let adjacency = snapshot.boundaries.adjacency();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
+
+use super::{datastore, Boundaries, Datastore};
+use crate::fso::asset::AssetId;
+use crate::fso::communes::{Abolitable, Dataset, Municipality, MunicipalityId, MunicipalityMode};
+use crate::tools::dataset::Datastore as _;
+use crate::tools::Downloader;
+use crate::Date;
+
+/// Releases of generalised boundaries by date of state
+#[derive(Clone, Debug, Default)]
+pub struct Releases {
+    releases: BTreeMap<Date, AssetId>,
+}
+impl Releases {
+    /// Add a release with the state at `date`
+    pub fn insert(&mut self, date: Date, asset: AssetId) {
+        self.releases.insert(date, asset);
+    }
+
+    /// Iterate on dates of state and assets of releases
+    pub fn iter(&self) -> impl Iterator<Item = (&Date, &AssetId)> {
+        self.releases.iter()
+    }
+
+    /// Last release with a state on or before `date`, with its date of state
+    pub fn at(&self, date: Date) -> Option<(Date, Datastore)> {
+        self.releases
+            .range(..=date)
+            .next_back()
+            .map(|(date, asset)| (*date, datastore(*asset)))
+    }
+
+    /// Load the release at `date` and check it with municipalities valid at
+    /// `date`
+    pub fn load_at<D>(
+        &self,
+        downloader: D,
+        municipalities: &Dataset<Municipality>,
+        date: Date,
+    ) -> Result<Snapshot, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        let (release, store) = self
+            .at(date)
+            .ok_or_else(|| format!("No release of boundaries at {date}"))?;
+        let boundaries = store.load(downloader)?;
+        let check = boundaries.check(municipalities, date)?;
+        Ok(Snapshot {
+            release,
+            boundaries,
+            check,
+        })
+    }
+}
+impl FromIterator<(Date, AssetId)> for Releases {
+    fn from_iter<I: IntoIterator<Item = (Date, AssetId)>>(iter: I) -> Self {
+        Self {
+            releases: iter.into_iter().collect(),
+        }
+    }
+}
+impl FromIterator<(i32, AssetId)> for Releases {
+    /// Releases by year, with the state on 1 January
+    fn from_iter<I: IntoIterator<Item = (i32, AssetId)>>(iter: I) -> Self {
+        iter.into_iter()
+            .filter_map(|(year, asset)| Some((Date::from_ymd_opt(year, 1, 1)?, asset)))
+            .collect()
+    }
+}
+
+/// Boundaries of a release loaded for a date
+pub struct Snapshot {
+    /// Date of state of release
+    pub release: Date,
+    /// Boundaries of release
+    pub boundaries: Boundaries,
+    /// Comparison with municipalities at the requested date
+    pub check: Check,
+}
+
+/// Differences between ids of boundaries and ids of municipalities
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Check {
+    /// Political communes valid at date without boundary
+    pub missing: BTreeSet<MunicipalityId>,
+    /// Boundaries without political commune valid at date
+    pub unknown: BTreeSet<MunicipalityId>,
+}
+impl Check {
+    /// Check if all ids match
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.unknown.is_empty()
+    }
+}
+impl fmt::Display for Check {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let join = |ids: &BTreeSet<MunicipalityId>| {
+            ids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            fmt,
+            "missing boundaries: [{}], unknown municipalities: [{}]",
+            join(&self.missing),
+            join(&self.unknown)
+        )
+    }
+}
+
+impl Boundaries {
+    /// Compare ids of boundaries with political communes valid at `date`
+    pub fn check(
+        &self,
+        municipalities: &Dataset<Municipality>,
+        date: Date,
+    ) -> Result<Check, csv::Error> {
+        let mut expected = BTreeSet::new();
+        for municipality in municipalities {
+            let municipality = municipality?;
+            if municipality.entry_mode == MunicipalityMode::PoliticalCommune
+                && municipality.validity().contains(&date)
+            {
+                expected.insert(municipality.id);
+            }
+        }
+        let found: BTreeSet<_> = self.iter().map(|boundary| boundary.id).collect();
+        Ok(Check {
+            missing: expected.difference(&found).copied().collect(),
+            unknown: found.difference(&expected).copied().collect(),
+        })
+    }
+}
@@ -43,10 +43,13 @@
 
 use std::collections::HashMap;
 use std::error;
+use std::fmt;
 use std::fs::File;
 use std::io::Cursor;
 use std::io::Read;
 use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use csv::{DeserializeRecordsIntoIter, ReaderBuilder as CsvReaderBuilder};
 use encoding_rs;
@@ -58,10 +61,28 @@ use zip::ZipArchive;
 
 use crate::fso::asset::{Asset, AssetId};
 use crate::i_serde;
+use crate::tools::validity::{Validity, ValidityIndex};
 use crate::tools::Downloader;
 use crate::tools::{dataset, meta};
 use crate::Date;
 
+pub mod count;
+#[cfg(feature = "polars")]
+pub mod data_frame;
+pub mod ech0007;
+pub mod ech0071;
+pub mod json;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+pub mod reorganisation;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod territory;
+
+pub use ech0007::SwissMunicipality;
+
 /// FSO Asset id for TXT format
 pub const TXT_ASSET_ID: AssetId = 23886071;
 /// FSO Asset id for XML format
@@ -103,16 +124,25 @@ where
 
 /// Get the communes FSO datastore
 pub fn datastore() -> Datastore {
-    Datastore { _none: () }
+    Datastore::default()
 }
 
 /// Load data (format TXT only for now) with downloader, keep a reference to
 /// zip file downloaded
 #[derive(Default)]
 pub struct Datastore {
-    _none: (),
+    sha256: Option<String>,
 }
 impl Datastore {
+    /// Pin the SHA-256 (hexadecimal) of zip file, [load] fail if the file
+    /// doesn't match (for reproducible runs)
+    ///
+    /// [load]: dataset::Datastore::load
+    pub fn with_sha256<S: Into<String>>(mut self, sha256: S) -> Self {
+        self.sha256 = Some(sha256.into());
+        self
+    }
+
     /// Get asset for text format (multiple CSV (tab separator) in a zip)
     pub fn asset(&self) -> Asset {
         TXT_ASSET_ID.into()
@@ -188,11 +218,17 @@ impl dataset::Datastore<&'static str> for Datastore {
         }
     }
 
+    fn urls(&self) -> Vec<String> {
+        vec![self.asset().url_data()]
+    }
+
     fn load<D>(&self, downloader: D) -> Result<Self::Store, Box<dyn error::Error>>
     where
         D: Downloader,
     {
-        let path = self.asset().data_file(downloader)?;
+        let path = self
+            .asset()
+            .data_file_verified(downloader, self.sha256.as_deref())?;
         let file = File::open(path)?;
         let mut zip = ZipArchive::new(file)?;
         let zippath: HashMap<String, String> = zip
@@ -225,7 +261,7 @@ impl dataset::Datastore<&'static str> for Datastore {
                 .build(zip.by_name(&fname)?)
                 .read_to_string(&mut output)?;
             Ok(Dataset {
-                raw: output,
+                raw: output.into_bytes().into(),
                 phantom: PhantomData,
             })
         }
@@ -239,6 +275,11 @@ impl dataset::Datastore<&'static str> for Datastore {
 }
 
 /// This struct contains all dataset can be retreive from data
+///
+/// Data are shared behind an [Arc], clone is cheap and this struct is [Send]
+/// and [Sync]. See [crate::tools::dataset::Shared] for share it between
+/// threads with hot reload.
+#[derive(Clone)]
 pub struct Datasets {
     /// Canton / Kanton / Canton
     pub cantons: Dataset<Canton>,
@@ -249,9 +290,19 @@ pub struct Datasets {
 }
 
 /// Represent a set of data, this is iterable
+///
+/// Raw data are shared, clone is cheap.
 pub struct Dataset<T> {
-    raw: String,
-    phantom: PhantomData<T>,
+    raw: Arc<[u8]>,
+    phantom: PhantomData<fn() -> T>,
+}
+impl<T> Clone for Dataset<T> {
+    fn clone(&self) -> Self {
+        Self {
+            raw: self.raw.clone(),
+            phantom: PhantomData,
+        }
+    }
 }
 impl<T> Dataset<T> {
     fn csv_reader_builder<'a>(
@@ -307,6 +358,17 @@ where
         self.into_iter()
     }
 }
+impl<T> Dataset<T>
+where
+    T: for<'de> Deserialize<'de> + Abolitable,
+{
+    /// Build an index for search records valid during a period
+    pub fn validity_index(&self) -> Result<ValidityIndex<T>, csv::Error> {
+        self.iter()
+            .map(|record| record.map(|record| (record.validity(), record)))
+            .collect()
+    }
+}
 impl<'a, T> IntoIterator for &'a Dataset<T>
 where
     T: for<'de> Deserialize<'de>,
@@ -317,7 +379,7 @@ where
     fn into_iter(self) -> Self::IntoIter {
         let mut builder = CsvReaderBuilder::new();
         self.csv_reader_builder(&mut builder)
-            .from_reader(Cursor::new(self.raw.as_bytes()))
+            .from_reader(Cursor::new(&self.raw[..]))
             .into_deserialize()
     }
 }
@@ -325,29 +387,127 @@ impl<T> IntoIterator for Dataset<T>
 where
     T: for<'de> Deserialize<'de>,
 {
-    type IntoIter = DeserializeRecordsIntoIter<Cursor<Vec<u8>>, T>;
+    type IntoIter = DeserializeRecordsIntoIter<Cursor<Arc<[u8]>>, T>;
     type Item = Result<T, csv::Error>;
 
     fn into_iter(self) -> Self::IntoIter {
         let mut builder = CsvReaderBuilder::new();
         self.csv_reader_builder(&mut builder)
-            .from_reader(Cursor::new(self.raw.into_bytes()))
+            .from_reader(Cursor::new(self.raw))
             .into_deserialize()
     }
 }
 
-/// Represent a identifier of canton
-pub type CantonId = u8;
-/// Represent a historical identifier of district
-pub type DistrictHistId = u32;
-/// Represent a identifier of district
-pub type DistrictId = u16;
-/// Represent a historical identifier of municipality
-pub type MunicipalityHistId = u32;
-/// Represent a identifier of municipality
-pub type MunicipalityId = u16;
-/// Represent a identifier of mutation (change, admission or abolition)
-pub type MutationId = u16;
+/// Error when parse or build an identifier
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdError {
+    kind: &'static str,
+    value: String,
+}
+impl fmt::Display for IdError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid {}: {:?}", self.kind, self.value)
+    }
+}
+impl error::Error for IdError {}
+
+macro_rules! identifier {
+    ($(#[$doc:meta])* $name:ident($int:ty), $min:literal..=$max:literal, width $width:literal) => {
+        $(#[$doc])*
+        #[derive(
+            Copy, Clone, Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Hash,
+        )]
+        #[serde(try_from = "u32", into = "u32")]
+        pub struct $name($int);
+        impl $name {
+            /// Smallest valid identifier
+            pub const MIN: Self = Self($min);
+            /// Biggest valid identifier
+            pub const MAX: Self = Self($max);
+
+            /// Build identifier, fail if out of range
+            pub fn new(id: $int) -> Result<Self, IdError> {
+                Self::try_from(u32::from(id))
+            }
+
+            /// Number of digits of the official format, see [Self::padded]
+            pub const DIGITS: usize = $width;
+
+            /// Get the numeric value
+            pub fn get(self) -> $int {
+                self.0
+            }
+
+            /// Official format, zero-padded to [Self::DIGITS] digits
+            ///
+            /// [Display](fmt::Display) and serialisations write the plain
+            /// number, like the FSO files.
+            pub fn padded(self) -> String {
+                format!("{:0width$}", self.0, width = $width)
+            }
+        }
+        impl TryFrom<u32> for $name {
+            type Error = IdError;
+
+            fn try_from(id: u32) -> Result<Self, Self::Error> {
+                match <$int>::try_from(id) {
+                    Ok(id) if ($min..=$max).contains(&id) => Ok(Self(id)),
+                    _ => Err(IdError {
+                        kind: stringify!($name),
+                        value: id.to_string(),
+                    }),
+                }
+            }
+        }
+        impl From<$name> for u32 {
+            fn from(id: $name) -> Self {
+                id.0.into()
+            }
+        }
+        impl fmt::Display for $name {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, fmt)
+            }
+        }
+        impl FromStr for $name {
+            type Err = IdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let err = || IdError {
+                    kind: stringify!($name),
+                    value: s.into(),
+                };
+                s.trim().parse::<u32>().map_err(|_| err())?.try_into().map_err(|_| err())
+            }
+        }
+    };
+}
+
+identifier! {
+    /// Represent a identifier of canton (1 to 26, two digits)
+    CantonId(u8), 1..=26, width 2
+}
+identifier! {
+    /// Represent a historical identifier of district (five digits)
+    DistrictHistId(u32), 1..=99999, width 5
+}
+identifier! {
+    /// Represent a identifier of district (four digits)
+    DistrictId(u16), 1..=9999, width 4
+}
+identifier! {
+    /// Represent a historical identifier of municipality (five digits)
+    MunicipalityHistId(u32), 1..=99999, width 5
+}
+identifier! {
+    /// Represent a identifier of municipality, the BFS number (four digits)
+    MunicipalityId(u16), 1..=9999, width 4
+}
+identifier! {
+    /// Represent a identifier of mutation (change, admission or abolition,
+    /// four digits)
+    MutationId(u16), 1..=9999, width 4
+}
 
 /// Status of municipality. All step (municipality, canton and national) are
 /// done if status is [Status::Final]
@@ -437,7 +597,7 @@ pub enum AbolitionMode {
 }
 
 /// Canton / Kanton / Canton
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Canton {
     /// Canton number / Kantonsnummer / Numéro du canton
     pub id: CantonId,
@@ -461,10 +621,18 @@ pub trait Abolitable {
     fn is_actual(&self) -> bool {
         self.abolition().is_none()
     }
+
+    /// Period of validity, from admission to abolition (inclusive)
+    fn validity(&self) -> Validity {
+        Validity::new(
+            *self.admission().date,
+            self.abolition().map(|abolition| *abolition.date),
+        )
+    }
 }
 
 /// Bezirk / District
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct District {
     /// Historic identifier
     /// / Historisierungsnummer BEZ
@@ -528,7 +696,7 @@ impl Abolitable for District {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 /// Municipality / Gemeinden / Commune
 pub struct Municipality {
     /// Municipality historical identifier
@@ -595,3 +763,51 @@ pub struct Mutation<'a, Mode: Copy> {
     /// Date of mutation
     pub date: &'a Date,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifier_range() {
+        assert!(CantonId::new(0).is_err());
+        assert_eq!(CantonId::new(1).unwrap(), CantonId::MIN);
+        assert_eq!(CantonId::new(26).unwrap(), CantonId::MAX);
+        assert!(CantonId::new(27).is_err());
+        assert!(MunicipalityId::new(10000).is_err());
+        assert!(MunicipalityHistId::new(99999).is_ok());
+        assert!(MunicipalityHistId::try_from(100000).is_err());
+        // out of range of inner integer
+        assert!(DistrictId::try_from(70000).is_err());
+    }
+
+    #[test]
+    fn identifier_text() {
+        let id: MunicipalityId = "261".parse().unwrap();
+        assert_eq!(id.get(), 261);
+        assert_eq!(id.to_string(), "261");
+        assert_eq!(id.padded(), "0261");
+        assert_eq!(" 0261 ".parse::<MunicipalityId>().unwrap(), id);
+        assert_eq!(id.padded().parse::<MunicipalityId>().unwrap(), id);
+        assert_eq!(CantonId::new(1).unwrap().padded(), "01");
+        assert_eq!(MutationId::new(42).unwrap().padded(), "0042");
+        for id in [CantonId::MIN, CantonId::MAX] {
+            assert_eq!(id.to_string().parse::<CantonId>().unwrap(), id);
+        }
+
+        assert!("".parse::<CantonId>().is_err());
+        assert!("ZH".parse::<CantonId>().is_err());
+        assert!("-1".parse::<CantonId>().is_err());
+        let error = "27".parse::<CantonId>().unwrap_err();
+        assert_eq!(error.to_string(), r#"invalid CantonId: "27""#);
+    }
+
+    #[test]
+    fn identifier_serde() {
+        let id = DistrictHistId::new(10053).unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), "10053");
+        assert_eq!(serde_json::from_str::<DistrictHistId>("10053").unwrap(), id);
+        assert!(serde_json::from_str::<DistrictHistId>("0").is_err());
+        assert_eq!(format!("{id}"), serde_json::to_string(&id).unwrap());
+    }
+}
@@ -0,0 +1,182 @@
+//! Count of municipalities per canton and district over time
+//!
+//! ```no_run
+//! use swissdata::fso::communes::{self, count, MunicipalityMode};
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::Date;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! let counts = ds
+//!     .municipalities
+//!     .count(count::yearly_dates(), &[MunicipalityMode::PoliticalCommune])?;
+//! let date = Date::from_ymd_opt(1950, 1, 1).unwrap();
+//! println!("FR in 1950: {:?}", counts.canton("FR", date));
+//! counts.write_csv(std::io::stdout())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use chrono::Datelike;
+
+use super::{Abolitable, Dataset, DistrictHistId, Municipality, MunicipalityMode};
+use crate::Date;
+
+/// First date of data (12 September 1848)
+pub fn first_date() -> Date {
+    Date::from_ymd_opt(1848, 9, 12).unwrap()
+}
+
+/// [first_date] and 1 January of every year from 1849 to today
+pub fn yearly_dates() -> Vec<Date> {
+    let today = chrono::Local::now().date_naive();
+    std::iter::once(first_date())
+        .chain((1849..=today.year()).filter_map(|year| Date::from_ymd_opt(year, 1, 1)))
+        .collect()
+}
+
+/// Number of municipalities per canton and district for a list of dates
+pub struct Counts {
+    dates: Vec<Date>,
+    cantons: BTreeMap<String, Vec<u32>>,
+    districts: BTreeMap<DistrictHistId, (String, Vec<u32>)>,
+}
+impl Counts {
+    /// Dates of counts (sorted)
+    pub fn dates(&self) -> &[Date] {
+        &self.dates
+    }
+
+    /// Number of municipalities of canton at `date` (must be in
+    /// [Counts::dates])
+    pub fn canton(&self, abbreviation: &str, date: Date) -> Option<u32> {
+        let i = self.dates.binary_search(&date).ok()?;
+        Some(self.cantons.get(abbreviation).map_or(0, |counts| counts[i]))
+    }
+
+    /// Number of municipalities of district at `date` (must be in
+    /// [Counts::dates])
+    pub fn district(&self, hist_id: DistrictHistId, date: Date) -> Option<u32> {
+        let i = self.dates.binary_search(&date).ok()?;
+        Some(
+            self.districts
+                .get(&hist_id)
+                .map_or(0, |(_, counts)| counts[i]),
+        )
+    }
+
+    /// Iterate on cantons (abbreviation) and counts for each date
+    pub fn cantons(&self) -> impl Iterator<Item = (&str, &[u32])> {
+        self.cantons
+            .iter()
+            .map(|(canton, counts)| (canton.as_str(), counts.as_slice()))
+    }
+
+    /// Iterate on districts (historical identifier and canton abbreviation)
+    /// and counts for each date
+    pub fn districts(&self) -> impl Iterator<Item = (DistrictHistId, &str, &[u32])> {
+        self.districts
+            .iter()
+            .map(|(id, (canton, counts))| (*id, canton.as_str(), counts.as_slice()))
+    }
+
+    /// Write counts on CSV
+    ///
+    /// Columns are `date`, `canton`, `district_hist_id` (empty for total of
+    /// canton) and `municipalities`. Rows with no municipality are skipped.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record(["date", "canton", "district_hist_id", "municipalities"])?;
+        for (i, date) in self.dates.iter().enumerate() {
+            let date = date.to_string();
+            for (canton, counts) in self.cantons() {
+                if counts[i] > 0 {
+                    csv.write_record([&date, canton, "", &counts[i].to_string()])?;
+                }
+            }
+            for (id, canton, counts) in self.districts() {
+                if counts[i] > 0 {
+                    csv.write_record([&date, canton, &id.to_string(), &counts[i].to_string()])?;
+                }
+            }
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+impl Dataset<Municipality> {
+    /// Count municipalities valid at each date, per canton and district
+    ///
+    /// Only municipalities with a mode in `modes` are counted, all if `modes`
+    /// is empty.
+    pub fn count<I>(&self, dates: I, modes: &[MunicipalityMode]) -> Result<Counts, csv::Error>
+    where
+        I: IntoIterator<Item = Date>,
+    {
+        let mut dates: Vec<_> = dates.into_iter().collect();
+        dates.sort();
+        dates.dedup();
+
+        // difference arrays, count at date i is sum of deltas 0..=i
+        let mut cantons: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+        let mut districts: BTreeMap<DistrictHistId, (String, Vec<i64>)> = BTreeMap::new();
+        for municipality in self {
+            let municipality = municipality?;
+            if !modes.is_empty() && !modes.contains(&municipality.entry_mode) {
+                continue;
+            }
+            let validity = municipality.validity();
+            let start = dates.partition_point(|date| date < &validity.from);
+            let end = match validity.to {
+                Some(to) => dates.partition_point(|date| date <= &to),
+                None => dates.len(),
+            };
+            if start >= end {
+                continue;
+            }
+            for deltas in [
+                cantons
+                    .entry(municipality.canton_abbreviation.clone())
+                    .or_insert_with(|| vec![0; dates.len() + 1]),
+                &mut districts
+                    .entry(municipality.district_hist_id)
+                    .or_insert_with(|| {
+                        (
+                            municipality.canton_abbreviation.clone(),
+                            vec![0; dates.len() + 1],
+                        )
+                    })
+                    .1,
+            ] {
+                deltas[start] += 1;
+                deltas[end] -= 1;
+            }
+        }
+
+        fn accumulate(deltas: Vec<i64>) -> Vec<u32> {
+            let mut sum = 0;
+            let mut counts: Vec<u32> = deltas
+                .into_iter()
+                .map(|delta| {
+                    sum += delta;
+                    sum as u32
+                })
+                .collect();
+            counts.pop();
+            counts
+        }
+        Ok(Counts {
+            dates,
+            cantons: cantons
+                .into_iter()
+                .map(|(canton, deltas)| (canton, accumulate(deltas)))
+                .collect(),
+            districts: districts
+                .into_iter()
+                .map(|(id, (canton, deltas))| (id, (canton, accumulate(deltas))))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,231 @@
+//! Conversion of records to Polars [DataFrame] (feature `polars`)
+//!
+//! Dates are `Date` columns, modes are categorical columns with the variant
+//! name (like `PoliticalCommune`) and abolition columns are nullable.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! let df = ds.municipalities.to_data_frame()?;
+//! println!("{df}");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::error;
+use std::fmt::Debug;
+
+use ::polars::prelude::{
+    CategoricalOrdering, Column, DataFrame, DataType, NamedFrom, PolarsResult, Series,
+};
+use serde::Deserialize;
+
+use super::{Canton, Dataset, District, Municipality};
+use crate::Date;
+
+/// Records convertible to a Polars [DataFrame]
+pub trait ToDataFrame: Sized {
+    /// Build a data frame with records
+    fn to_data_frame(records: &[Self]) -> PolarsResult<DataFrame>;
+}
+
+impl<T> Dataset<T>
+where
+    T: for<'de> Deserialize<'de> + ToDataFrame,
+{
+    /// Convert all records to a Polars [DataFrame]
+    pub fn to_data_frame(&self) -> Result<DataFrame, Box<dyn error::Error>> {
+        let records = self.iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(T::to_data_frame(&records)?)
+    }
+}
+
+fn column<T, V>(name: &str, values: V) -> Column
+where
+    Series: NamedFrom<V, T>,
+    T: ?Sized,
+{
+    Series::new(name.into(), values).into()
+}
+
+fn dates<'a, I>(name: &str, dates: I) -> PolarsResult<Column>
+where
+    I: IntoIterator<Item = Option<&'a Date>>,
+{
+    let epoch = Date::from_ymd_opt(1970, 1, 1).unwrap();
+    let days: Vec<_> = dates
+        .into_iter()
+        .map(|date| date.map(|date| (*date - epoch).num_days() as i32))
+        .collect();
+    Ok(Series::new(name.into(), days).cast(&DataType::Date)?.into())
+}
+
+fn modes<I, M>(name: &str, modes: I) -> PolarsResult<Column>
+where
+    I: IntoIterator<Item = Option<M>>,
+    M: Debug,
+{
+    let names: Vec<_> = modes
+        .into_iter()
+        .map(|mode| mode.map(|mode| format!("{mode:?}")))
+        .collect();
+    Ok(Series::new(name.into(), names)
+        .cast(&DataType::Categorical(None, CategoricalOrdering::Physical))?
+        .into())
+}
+
+impl ToDataFrame for Canton {
+    fn to_data_frame(records: &[Self]) -> PolarsResult<DataFrame> {
+        DataFrame::new(vec![
+            column("id", records.iter().map(|r| r.id.get()).collect::<Vec<_>>()),
+            column(
+                "abbreviation",
+                records
+                    .iter()
+                    .map(|r| r.abbreviation.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            column(
+                "name",
+                records.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+            ),
+            dates(
+                "date_of_change",
+                records.iter().map(|r| Some(&r.date_of_change)),
+            )?,
+        ])
+    }
+}
+
+impl ToDataFrame for District {
+    fn to_data_frame(records: &[Self]) -> PolarsResult<DataFrame> {
+        DataFrame::new(vec![
+            column(
+                "hist_id",
+                records.iter().map(|r| r.hist_id.get()).collect::<Vec<_>>(),
+            ),
+            column(
+                "canton_id",
+                records
+                    .iter()
+                    .map(|r| r.canton_id.get())
+                    .collect::<Vec<_>>(),
+            ),
+            column("id", records.iter().map(|r| r.id.get()).collect::<Vec<_>>()),
+            column(
+                "name",
+                records.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+            ),
+            column(
+                "short_name",
+                records
+                    .iter()
+                    .map(|r| r.short_name.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            modes("entry_mode", records.iter().map(|r| Some(r.entry_mode)))?,
+            column(
+                "admission_number",
+                records
+                    .iter()
+                    .map(|r| r.admission_number.get())
+                    .collect::<Vec<_>>(),
+            ),
+            modes(
+                "admission_mode",
+                records.iter().map(|r| Some(r.admission_mode)),
+            )?,
+            dates(
+                "admission_date",
+                records.iter().map(|r| Some(&r.admission_date)),
+            )?,
+            column(
+                "abolition_number",
+                records
+                    .iter()
+                    .map(|r| r.abolition_number.map(|n| n.get()))
+                    .collect::<Vec<_>>(),
+            ),
+            modes("abolition_mode", records.iter().map(|r| r.abolition_mode))?,
+            dates(
+                "abolition_date",
+                records.iter().map(|r| r.abolition_date.as_ref()),
+            )?,
+            dates(
+                "date_of_change",
+                records.iter().map(|r| Some(&r.date_of_change)),
+            )?,
+        ])
+    }
+}
+
+impl ToDataFrame for Municipality {
+    fn to_data_frame(records: &[Self]) -> PolarsResult<DataFrame> {
+        DataFrame::new(vec![
+            column(
+                "hist_id",
+                records.iter().map(|r| r.hist_id.get()).collect::<Vec<_>>(),
+            ),
+            column(
+                "district_hist_id",
+                records
+                    .iter()
+                    .map(|r| r.district_hist_id.get())
+                    .collect::<Vec<_>>(),
+            ),
+            column(
+                "canton_abbreviation",
+                records
+                    .iter()
+                    .map(|r| r.canton_abbreviation.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            column("id", records.iter().map(|r| r.id.get()).collect::<Vec<_>>()),
+            column(
+                "name",
+                records.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+            ),
+            column(
+                "short_name",
+                records
+                    .iter()
+                    .map(|r| r.short_name.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            modes("entry_mode", records.iter().map(|r| Some(r.entry_mode)))?,
+            modes("status", records.iter().map(|r| Some(r.status)))?,
+            column(
+                "admission_number",
+                records
+                    .iter()
+                    .map(|r| r.admission_number.get())
+                    .collect::<Vec<_>>(),
+            ),
+            modes(
+                "admission_mode",
+                records.iter().map(|r| Some(r.admission_mode)),
+            )?,
+            dates(
+                "admission_date",
+                records.iter().map(|r| Some(&r.admission_date)),
+            )?,
+            column(
+                "abolition_number",
+                records
+                    .iter()
+                    .map(|r| r.abolition_number.map(|n| n.get()))
+                    .collect::<Vec<_>>(),
+            ),
+            modes("abolition_mode", records.iter().map(|r| r.abolition_mode))?,
+            dates(
+                "abolition_date",
+                records.iter().map(|r| r.abolition_date.as_ref()),
+            )?,
+            dates(
+                "date_of_change",
+                records.iter().map(|r| Some(&r.date_of_change)),
+            )?,
+        ])
+    }
+}
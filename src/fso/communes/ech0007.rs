@@ -0,0 +1,174 @@
+//! Municipality on eCH-0007 format
+//!
+//! - eCH-0007 norm [[de][eCH-0007-de]] [[fr][eCH-0007-fr]]
+//!
+//! [eCH-0007-de]: https://www.ech.ch/de/ech/ech-0007/5.0
+//! [eCH-0007-fr]: https://www.ech.ch/fr/ech/ech-0007/5.0
+
+use std::error;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer};
+use serde::{Deserialize, Serialize};
+
+use super::{Abolitable, Datasets, Municipality, MunicipalityHistId, MunicipalityId};
+use crate::Date;
+
+/// XML namespace of eCH-0007 version 5
+pub const XMLNS: &str = "http://www.ech.ch/xmlns/eCH-0007/5";
+
+/// Municipality on eCH-0007 format (`swissMunicipalityType`)
+///
+/// Serialize with serde (JSON, ...) or [SwissMunicipality::to_xml]. For
+/// deserialize and check the municipality exists in data, use [Validated]
+/// (or [SwissMunicipality::from_json] and [SwissMunicipality::from_xml]).
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename = "swissMunicipality", rename_all = "camelCase")]
+pub struct SwissMunicipality {
+    /// BFS number of municipality
+    pub municipality_id: MunicipalityId,
+    /// Official name of municipality
+    pub municipality_name: String,
+    /// Abbreviation of canton (two uppercase letter)
+    pub canton_abbreviation: String,
+    /// Historical identifier of municipality
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_municipality_id: Option<MunicipalityHistId>,
+}
+impl SwissMunicipality {
+    /// Get the municipality `id` valid at `date`
+    pub fn at(
+        datasets: &Datasets,
+        id: MunicipalityId,
+        date: Date,
+    ) -> Result<Option<Self>, csv::Error> {
+        for municipality in &datasets.municipalities {
+            let municipality = municipality?;
+            if municipality.id == id && municipality.validity().contains(&date) {
+                return Ok(Some(municipality.into()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check if this municipality exists in `datasets`
+    ///
+    /// If [SwissMunicipality::history_municipality_id] is defined, the
+    /// historical record must match, otherwise any record with same id, name
+    /// and canton is accepted.
+    pub fn validate(&self, datasets: &Datasets) -> Result<(), Box<dyn error::Error>> {
+        for municipality in &datasets.municipalities {
+            let municipality = municipality?;
+            if self.history_municipality_id.is_some()
+                && self.history_municipality_id != Some(municipality.hist_id)
+            {
+                continue;
+            }
+            if municipality.id == self.municipality_id
+                && municipality.name == self.municipality_name
+                && municipality.canton_abbreviation == self.canton_abbreviation
+            {
+                return Ok(());
+            }
+        }
+        Err(format!(
+            "unknown municipality {} {} ({})",
+            self.municipality_id, self.municipality_name, self.canton_abbreviation
+        )
+        .into())
+    }
+
+    /// Serialize on eCH-0007 XML
+    pub fn to_xml(&self) -> Result<String, quick_xml::DeError> {
+        #[derive(Serialize)]
+        #[serde(rename = "swissMunicipality", rename_all = "camelCase")]
+        struct Xml<'a> {
+            #[serde(rename = "@xmlns")]
+            xmlns: &'static str,
+            municipality_id: MunicipalityId,
+            municipality_name: &'a str,
+            canton_abbreviation: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            history_municipality_id: Option<MunicipalityHistId>,
+        }
+        quick_xml::se::to_string(&Xml {
+            xmlns: XMLNS,
+            municipality_id: self.municipality_id,
+            municipality_name: &self.municipality_name,
+            canton_abbreviation: &self.canton_abbreviation,
+            history_municipality_id: self.history_municipality_id,
+        })
+    }
+
+    /// Deserialize eCH-0007 XML and check municipality exists in `datasets`
+    pub fn from_xml(xml: &str, datasets: &Datasets) -> Result<Self, quick_xml::DeError> {
+        Validated(datasets).deserialize(&mut quick_xml::de::Deserializer::from_str(xml))
+    }
+
+    /// Serialize on JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize JSON and check municipality exists in `datasets`
+    pub fn from_json(json: &str, datasets: &Datasets) -> Result<Self, serde_json::Error> {
+        Validated(datasets).deserialize(&mut serde_json::Deserializer::from_str(json))
+    }
+}
+impl From<&Municipality> for SwissMunicipality {
+    fn from(municipality: &Municipality) -> Self {
+        Self {
+            municipality_id: municipality.id,
+            municipality_name: municipality.name.clone(),
+            canton_abbreviation: municipality.canton_abbreviation.clone(),
+            history_municipality_id: Some(municipality.hist_id),
+        }
+    }
+}
+impl From<Municipality> for SwissMunicipality {
+    fn from(municipality: Municipality) -> Self {
+        Self {
+            municipality_id: municipality.id,
+            municipality_name: municipality.name,
+            canton_abbreviation: municipality.canton_abbreviation,
+            history_municipality_id: Some(municipality.hist_id),
+        }
+    }
+}
+impl fmt::Display for SwissMunicipality {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{} {} ({})",
+            self.municipality_id, self.municipality_name, self.canton_abbreviation
+        )
+    }
+}
+
+/// Deserialize a [SwissMunicipality] and check it exists in data
+///
+/// ```no_run
+/// use serde::de::DeserializeSeed;
+/// use swissdata::fso::communes::{self, ech0007::Validated};
+/// use swissdata::tools::dataset::Datastore;
+///
+/// let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+/// let json = r#"{"municipalityId":261,"municipalityName":"Zürich","cantonAbbreviation":"ZH"}"#;
+/// let municipality =
+///     Validated(&ds).deserialize(&mut serde_json::Deserializer::from_str(json))?;
+/// println!("{municipality}");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Validated<'a>(pub &'a Datasets);
+impl<'de, 'a> DeserializeSeed<'de> for Validated<'a> {
+    type Value = SwissMunicipality;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let municipality = SwissMunicipality::deserialize(deserializer)?;
+        municipality.validate(self.0).map_err(de::Error::custom)?;
+        Ok(municipality)
+    }
+}
@@ -0,0 +1,412 @@
+//! Export on eCH-0071 XML format
+//!
+//! Follow the structure of the XML of FSO (see [super::Datastore::asset_xml]),
+//! for validate output with the XSD shipped in this archive.
+//!
+//! - eCH-0071 norm [[de][eCH-0071-de]] [[fr][eCH-0071-fr]]
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::Date;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! ds.write_ech0071(File::create("communes.xml")?)?;
+//! ds.write_ech0071_at(
+//!     File::create("communes-2000.xml")?,
+//!     Date::from_ymd_opt(2000, 1, 1).unwrap(),
+//! )?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [eCH-0071-de]: https://www.ech.ch/de/ech/ech-0071/1.1
+//! [eCH-0071-fr]: https://www.ech.ch/fr/ech/ech-0071/1.1
+
+use std::error;
+use std::fmt::Display;
+use std::io::Write;
+
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Writer;
+
+use super::{Abolitable, Canton, Datasets, District, Municipality};
+use crate::Date;
+
+/// XML namespace of eCH-0071 version 1
+pub const XMLNS: &str = "http://www.ech.ch/xmlns/eCH-0071/1";
+
+/// Write records as an eCH-0071 `nomenclature` document
+///
+/// Records can be any subset of [Datasets], errors of iterators are returned
+/// (like [csv::Error] when iterate directly on a [super::Dataset]).
+pub fn write<W, C, D, M, E>(
+    writer: W,
+    cantons: C,
+    districts: D,
+    municipalities: M,
+) -> Result<W, Box<dyn error::Error>>
+where
+    W: Write,
+    C: IntoIterator<Item = Result<Canton, E>>,
+    D: IntoIterator<Item = Result<District, E>>,
+    M: IntoIterator<Item = Result<Municipality, E>>,
+    E: error::Error + 'static,
+{
+    let mut xml = Writer::new_with_indent(writer, b' ', 2);
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    xml.write_event(Event::Start(
+        BytesStart::new("nomenclature").with_attributes([("xmlns", XMLNS)]),
+    ))?;
+
+    start(&mut xml, "cantons")?;
+    for canton in cantons {
+        write_canton(&mut xml, &canton?)?;
+    }
+    end(&mut xml, "cantons")?;
+
+    start(&mut xml, "districts")?;
+    for district in districts {
+        write_district(&mut xml, &district?)?;
+    }
+    end(&mut xml, "districts")?;
+
+    start(&mut xml, "municipalities")?;
+    for municipality in municipalities {
+        write_municipality(&mut xml, &municipality?)?;
+    }
+    end(&mut xml, "municipalities")?;
+
+    end(&mut xml, "nomenclature")?;
+    Ok(xml.into_inner())
+}
+
+impl Datasets {
+    /// Write all records as eCH-0071 XML
+    pub fn write_ech0071<W: Write>(&self, writer: W) -> Result<W, Box<dyn error::Error>> {
+        write(writer, &self.cantons, &self.districts, &self.municipalities)
+    }
+
+    /// Write records valid at `date` as eCH-0071 XML
+    pub fn write_ech0071_at<W: Write>(
+        &self,
+        writer: W,
+        date: Date,
+    ) -> Result<W, Box<dyn error::Error>> {
+        write(
+            writer,
+            &self.cantons,
+            self.districts.iter().filter(valid_at(date)),
+            self.municipalities.iter().filter(valid_at(date)),
+        )
+    }
+}
+
+/// Filter records valid at `date`, errors are kept
+fn valid_at<T: Abolitable, E>(date: Date) -> impl Fn(&Result<T, E>) -> bool {
+    move |record| match record {
+        Ok(record) => record.validity().contains(&date),
+        Err(_) => true,
+    }
+}
+
+fn start<W: Write>(xml: &mut Writer<W>, name: &str) -> quick_xml::Result<()> {
+    xml.write_event(Event::Start(BytesStart::new(name)))
+}
+
+fn end<W: Write>(xml: &mut Writer<W>, name: &str) -> quick_xml::Result<()> {
+    xml.write_event(Event::End(BytesEnd::new(name)))
+}
+
+fn element<W: Write, V: Display>(
+    xml: &mut Writer<W>,
+    name: &str,
+    value: V,
+) -> quick_xml::Result<()> {
+    xml.create_element(name)
+        .write_text_content(BytesText::new(&value.to_string()))?;
+    Ok(())
+}
+
+fn optional<W: Write, V: Display>(
+    xml: &mut Writer<W>,
+    name: &str,
+    value: Option<V>,
+) -> quick_xml::Result<()> {
+    match value {
+        Some(value) => element(xml, name, value),
+        None => Ok(()),
+    }
+}
+
+fn write_canton<W: Write>(xml: &mut Writer<W>, canton: &Canton) -> quick_xml::Result<()> {
+    start(xml, "canton")?;
+    element(xml, "cantonId", canton.id.get())?;
+    element(xml, "cantonAbbreviation", &canton.abbreviation)?;
+    element(xml, "cantonLongName", &canton.name)?;
+    element(xml, "cantonDateOfChange", canton.date_of_change)?;
+    end(xml, "canton")
+}
+
+fn write_district<W: Write>(xml: &mut Writer<W>, district: &District) -> quick_xml::Result<()> {
+    start(xml, "district")?;
+    element(xml, "districtHistId", district.hist_id.get())?;
+    element(xml, "cantonId", district.canton_id.get())?;
+    element(xml, "districtId", district.id.get())?;
+    element(xml, "districtLongName", &district.name)?;
+    element(xml, "districtShortName", &district.short_name)?;
+    element(xml, "districtEntryMode", district.entry_mode as u8)?;
+    element(
+        xml,
+        "districtAdmissionNumber",
+        district.admission_number.get(),
+    )?;
+    element(xml, "districtAdmissionMode", district.admission_mode as u8)?;
+    element(xml, "districtAdmissionDate", district.admission_date)?;
+    optional(
+        xml,
+        "districtAbolitionNumber",
+        district.abolition_number.map(|n| n.get()),
+    )?;
+    optional(
+        xml,
+        "districtAbolitionMode",
+        district.abolition_mode.map(|m| m as u8),
+    )?;
+    optional(xml, "districtAbolitionDate", district.abolition_date)?;
+    element(xml, "districtDateOfChange", district.date_of_change)?;
+    end(xml, "district")
+}
+
+fn write_municipality<W: Write>(
+    xml: &mut Writer<W>,
+    municipality: &Municipality,
+) -> quick_xml::Result<()> {
+    start(xml, "municipality")?;
+    element(xml, "historyMunicipalityId", municipality.hist_id.get())?;
+    element(xml, "districtHistId", municipality.district_hist_id.get())?;
+    element(xml, "cantonAbbreviation", &municipality.canton_abbreviation)?;
+    element(xml, "municipalityId", municipality.id.get())?;
+    element(xml, "municipalityLongName", &municipality.name)?;
+    element(xml, "municipalityShortName", &municipality.short_name)?;
+    element(xml, "municipalityEntryMode", municipality.entry_mode as u8)?;
+    element(xml, "municipalityStatus", municipality.status as u8)?;
+    element(
+        xml,
+        "municipalityAdmissionNumber",
+        municipality.admission_number.get(),
+    )?;
+    element(
+        xml,
+        "municipalityAdmissionMode",
+        municipality.admission_mode as u8,
+    )?;
+    element(
+        xml,
+        "municipalityAdmissionDate",
+        municipality.admission_date,
+    )?;
+    optional(
+        xml,
+        "municipalityAbolitionNumber",
+        municipality.abolition_number.map(|n| n.get()),
+    )?;
+    optional(
+        xml,
+        "municipalityAbolitionMode",
+        municipality.abolition_mode.map(|m| m as u8),
+    )?;
+    optional(
+        xml,
+        "municipalityAbolitionDate",
+        municipality.abolition_date,
+    )?;
+    element(xml, "municipalityDateOfChange", municipality.date_of_change)?;
+    end(xml, "municipality")
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::fso::communes;
+    use crate::tools::dataset::Datastore;
+    use crate::tools::downloader::mock::Mock;
+    use crate::tools::internal::testing::communes_zip;
+
+    #[derive(Deserialize)]
+    struct Nomenclature {
+        cantons: Cantons,
+        districts: Districts,
+        municipalities: Municipalities,
+    }
+    #[derive(Deserialize)]
+    struct Cantons {
+        #[serde(default)]
+        canton: Vec<XmlCanton>,
+    }
+    #[derive(Deserialize)]
+    struct Districts {
+        #[serde(default)]
+        district: Vec<XmlDistrict>,
+    }
+    #[derive(Deserialize)]
+    struct Municipalities {
+        #[serde(default)]
+        municipality: Vec<XmlMunicipality>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct XmlCanton {
+        canton_id: u8,
+        canton_abbreviation: String,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct XmlDistrict {
+        district_hist_id: u32,
+        district_long_name: String,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct XmlMunicipality {
+        history_municipality_id: u32,
+        municipality_id: u16,
+        municipality_long_name: String,
+        municipality_admission_date: String,
+        municipality_abolition_date: Option<String>,
+    }
+
+    fn datasets() -> Datasets {
+        let url = communes::datastore().urls().remove(0);
+        let mock = Mock::new().with_content(url, communes_zip());
+        communes::datastore().load(&mock).unwrap()
+    }
+
+    /// Names of child elements of the first element `parent`
+    fn children(xml: &str, parent: &str) -> Vec<String> {
+        let mut reader = Reader::from_str(xml);
+        let mut names = Vec::new();
+        let mut depth = None;
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) => {
+                    let name = String::from_utf8(e.name().as_ref().to_vec()).unwrap();
+                    match depth {
+                        None if name == parent => depth = Some(0),
+                        Some(0) => {
+                            names.push(name);
+                            depth = Some(1);
+                        }
+                        Some(d) => depth = Some(d + 1),
+                        None => {}
+                    }
+                }
+                Event::End(_) => match depth {
+                    Some(0) => return names,
+                    Some(d) => depth = Some(d - 1),
+                    None => {}
+                },
+                Event::Eof => return names,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let ds = datasets();
+        let xml = String::from_utf8(ds.write_ech0071(Vec::new()).unwrap()).unwrap();
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(xml.contains(&format!(r#"<nomenclature xmlns="{XMLNS}">"#)));
+
+        let nomenclature: Nomenclature = quick_xml::de::from_str(&xml).unwrap();
+        let [canton] = &nomenclature.cantons.canton[..] else {
+            panic!("one canton expected");
+        };
+        assert_eq!((canton.canton_id, &*canton.canton_abbreviation), (1, "ZH"));
+        let [district] = &nomenclature.districts.district[..] else {
+            panic!("one district expected");
+        };
+        assert_eq!(district.district_hist_id, 10053);
+        assert_eq!(district.district_long_name, "Bezirk Affoltern");
+
+        let expected = ds.municipalities.iter().next().unwrap().unwrap();
+        let [municipality] = &nomenclature.municipalities.municipality[..] else {
+            panic!("one municipality expected");
+        };
+        assert_eq!(municipality.history_municipality_id, expected.hist_id.get());
+        assert_eq!(municipality.municipality_id, expected.id.get());
+        assert_eq!(municipality.municipality_long_name, expected.name);
+        // xs:date
+        assert_eq!(municipality.municipality_admission_date, "1960-01-01");
+        assert_eq!(municipality.municipality_abolition_date, None);
+    }
+
+    #[test]
+    fn element_order() {
+        let ds = datasets();
+        let xml = String::from_utf8(ds.write_ech0071(Vec::new()).unwrap()).unwrap();
+        assert_eq!(
+            children(&xml, "nomenclature"),
+            ["cantons", "districts", "municipalities"]
+        );
+        assert_eq!(
+            children(&xml, "canton"),
+            [
+                "cantonId",
+                "cantonAbbreviation",
+                "cantonLongName",
+                "cantonDateOfChange"
+            ]
+        );
+        assert_eq!(
+            children(&xml, "district"),
+            [
+                "districtHistId",
+                "cantonId",
+                "districtId",
+                "districtLongName",
+                "districtShortName",
+                "districtEntryMode",
+                "districtAdmissionNumber",
+                "districtAdmissionMode",
+                "districtAdmissionDate",
+                "districtDateOfChange"
+            ]
+        );
+        assert_eq!(
+            children(&xml, "municipality"),
+            [
+                "historyMunicipalityId",
+                "districtHistId",
+                "cantonAbbreviation",
+                "municipalityId",
+                "municipalityLongName",
+                "municipalityShortName",
+                "municipalityEntryMode",
+                "municipalityStatus",
+                "municipalityAdmissionNumber",
+                "municipalityAdmissionMode",
+                "municipalityAdmissionDate",
+                "municipalityDateOfChange"
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_at_date() {
+        let ds = datasets();
+        let before = Date::from_ymd_opt(1950, 1, 1).unwrap();
+        let xml = ds.write_ech0071_at(Vec::new(), before).unwrap();
+        let nomenclature: Nomenclature =
+            quick_xml::de::from_str(std::str::from_utf8(&xml).unwrap()).unwrap();
+        assert_eq!(nomenclature.cantons.canton.len(), 1);
+        assert!(nomenclature.districts.district.is_empty());
+        assert!(nomenclature.municipalities.municipality.is_empty());
+    }
+}
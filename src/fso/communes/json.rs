@@ -0,0 +1,233 @@
+//! Export the canton → district → municipality hierarchy on JSON
+//!
+//! - [Datasets::to_json_tree]: nested tree of cantons, districts and
+//!   municipalities
+//! - [Datasets::write_ndjson]: one record per line, with resolved parents
+//!
+//! Dates are on ISO format (`YYYY-MM-DD`), modes are their numeric code.
+//!
+//! ```no_run
+//! use swissdata::fso::communes::{self, json::JsonOptions};
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::Date;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! let date = Date::from_ymd_opt(2000, 1, 1).unwrap();
+//! let tree = ds.to_json_tree(date, JsonOptions::default())?;
+//! println!("{tree:#}");
+//! let options = JsonOptions {
+//!     historic: true,
+//!     mutations: true,
+//! };
+//! ds.write_ndjson(std::io::stdout(), date, options)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::error;
+use std::io::Write;
+
+use serde_json::{json, Map, Value};
+
+use super::{Abolitable, Canton, CantonId, Datasets, District, DistrictHistId, Municipality};
+use crate::Date;
+
+/// Options of JSON export
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JsonOptions {
+    /// Include entries admitted before the date but already abolished
+    pub historic: bool,
+    /// Include admission and abolition of entries
+    pub mutations: bool,
+}
+impl JsonOptions {
+    fn selected<T: Abolitable>(&self, record: &T, date: &Date) -> bool {
+        let validity = record.validity();
+        if self.historic {
+            &validity.from <= date
+        } else {
+            validity.contains(date)
+        }
+    }
+
+    fn mutations<T: Abolitable>(&self, object: &mut Map<String, Value>, record: &T) {
+        if !self.mutations {
+            return;
+        }
+        let admission = record.admission();
+        object.insert(
+            "admission".into(),
+            json!({
+                "number": admission.number,
+                "mode": admission.mode as u8,
+                "date": admission.date.to_string(),
+            }),
+        );
+        object.insert(
+            "abolition".into(),
+            match record.abolition() {
+                Some(abolition) => json!({
+                    "number": abolition.number,
+                    "mode": abolition.mode as u8,
+                    "date": abolition.date.to_string(),
+                }),
+                None => Value::Null,
+            },
+        );
+    }
+}
+
+fn canton_json(canton: &Canton) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert("id".into(), json!(canton.id));
+    object.insert("abbreviation".into(), json!(canton.abbreviation));
+    object.insert("name".into(), json!(canton.name));
+    object
+}
+
+fn district_json(district: &District, options: &JsonOptions) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert("hist_id".into(), json!(district.hist_id));
+    object.insert("id".into(), json!(district.id));
+    object.insert("name".into(), json!(district.name));
+    object.insert("short_name".into(), json!(district.short_name));
+    object.insert("entry_mode".into(), json!(district.entry_mode as u8));
+    options.mutations(&mut object, district);
+    object
+}
+
+fn municipality_json(municipality: &Municipality, options: &JsonOptions) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert("hist_id".into(), json!(municipality.hist_id));
+    object.insert("id".into(), json!(municipality.id));
+    object.insert("name".into(), json!(municipality.name));
+    object.insert("short_name".into(), json!(municipality.short_name));
+    object.insert("entry_mode".into(), json!(municipality.entry_mode as u8));
+    object.insert("status".into(), json!(municipality.status as u8));
+    options.mutations(&mut object, municipality);
+    object
+}
+
+impl Datasets {
+    /// Tree of cantons, with their districts, with their municipalities at
+    /// `date`
+    pub fn to_json_tree(
+        &self,
+        date: Date,
+        options: JsonOptions,
+    ) -> Result<Value, Box<dyn error::Error>> {
+        let mut municipalities: HashMap<DistrictHistId, Vec<Value>> = HashMap::new();
+        for municipality in &self.municipalities {
+            let municipality = municipality?;
+            if options.selected(&municipality, &date) {
+                municipalities
+                    .entry(municipality.district_hist_id)
+                    .or_default()
+                    .push(municipality_json(&municipality, &options).into());
+            }
+        }
+        let mut districts: HashMap<CantonId, Vec<Value>> = HashMap::new();
+        for district in &self.districts {
+            let district = district?;
+            if options.selected(&district, &date) {
+                let mut object = district_json(&district, &options);
+                object.insert(
+                    "municipalities".into(),
+                    municipalities
+                        .remove(&district.hist_id)
+                        .unwrap_or_default()
+                        .into(),
+                );
+                districts
+                    .entry(district.canton_id)
+                    .or_default()
+                    .push(object.into());
+            }
+        }
+        let mut cantons = Vec::new();
+        for canton in &self.cantons {
+            let canton = canton?;
+            let mut object = canton_json(&canton);
+            object.insert(
+                "districts".into(),
+                districts.remove(&canton.id).unwrap_or_default().into(),
+            );
+            cantons.push(Value::from(object));
+        }
+        Ok(cantons.into())
+    }
+
+    /// Write one JSON object per line (NDJSON) for each entry at `date`
+    ///
+    /// Each object has a `type` (`canton`, `district` or `municipality`) and
+    /// the parents are resolved (`canton` for districts, `district` and
+    /// `canton` for municipalities).
+    pub fn write_ndjson<W: Write>(
+        &self,
+        mut writer: W,
+        date: Date,
+        options: JsonOptions,
+    ) -> Result<W, Box<dyn error::Error>> {
+        let mut cantons: HashMap<String, Map<String, Value>> = HashMap::new();
+        let mut canton_ids: HashMap<CantonId, String> = HashMap::new();
+        for canton in &self.cantons {
+            let canton = canton?;
+            let object = canton_json(&canton);
+            let mut line = object.clone();
+            line.insert("type".into(), "canton".into());
+            serde_json::to_writer(&mut writer, &line)?;
+            writeln!(writer)?;
+            canton_ids.insert(canton.id, canton.abbreviation.clone());
+            cantons.insert(canton.abbreviation, object);
+        }
+        let canton_by_id = |id: &CantonId| {
+            canton_ids
+                .get(id)
+                .and_then(|abbreviation| cantons.get(abbreviation))
+                .cloned()
+                .map_or(Value::Null, Value::from)
+        };
+
+        let mut districts: HashMap<DistrictHistId, Map<String, Value>> = HashMap::new();
+        for district in &self.districts {
+            let district = district?;
+            let mut parent = Map::new();
+            parent.insert("hist_id".into(), json!(district.hist_id));
+            parent.insert("id".into(), json!(district.id));
+            parent.insert("name".into(), json!(district.name));
+            districts.insert(district.hist_id, parent);
+            if options.selected(&district, &date) {
+                let mut line = district_json(&district, &options);
+                line.insert("type".into(), "district".into());
+                line.insert("canton".into(), canton_by_id(&district.canton_id));
+                serde_json::to_writer(&mut writer, &line)?;
+                writeln!(writer)?;
+            }
+        }
+
+        for municipality in &self.municipalities {
+            let municipality = municipality?;
+            if options.selected(&municipality, &date) {
+                let mut line = municipality_json(&municipality, &options);
+                line.insert("type".into(), "municipality".into());
+                line.insert(
+                    "district".into(),
+                    districts
+                        .get(&municipality.district_hist_id)
+                        .cloned()
+                        .map_or(Value::Null, Value::from),
+                );
+                line.insert(
+                    "canton".into(),
+                    cantons
+                        .get(&municipality.canton_abbreviation)
+                        .cloned()
+                        .map_or(Value::Null, Value::from),
+                );
+                serde_json::to_writer(&mut writer, &line)?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(writer)
+    }
+}
@@ -0,0 +1,174 @@
+//! Export datasets on Parquet files (feature `parquet`)
+//!
+//! Files are written on a Hive-style layout, one directory per table
+//! (`cantons`, `districts` and `municipalities`) and one sub-directory per
+//! partition:
+//!
+//! ```text
+//! <dir>/municipalities/snapshot=2000-01-01/canton=ZH/data.parquet
+//! ```
+//!
+//! [Meta] information (editor, terms, citations, ...) is embedded in the
+//! key-value metadata of each file (see [Meta::to_key_values]).
+//!
+//! ```no_run
+//! use swissdata::fso::communes::{self, count, parquet_export::ParquetOptions};
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let store = communes::datastore();
+//! let ds = store.load(&reqwest::blocking::Client::new())?;
+//! let options = ParquetOptions {
+//!     snapshots: count::yearly_dates(),
+//!     by_canton: true,
+//! };
+//! let files = ds.write_parquet("communes", &store.meta(), &options)?;
+//! println!("{} files written", files.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::file::metadata::KeyValue;
+use ::parquet::file::properties::WriterProperties;
+
+use super::record_batch::ToRecordBatch;
+use super::{Abolitable, CantonId, Datasets};
+use crate::tools::meta::Meta;
+use crate::Date;
+
+/// Options of Parquet export
+#[derive(Clone, Debug, Default)]
+pub struct ParquetOptions {
+    /// Write a snapshot of records valid at each date (partition
+    /// `snapshot=YYYY-MM-DD`), all records if empty
+    pub snapshots: Vec<Date>,
+    /// Partition by canton (partition `canton=XX`)
+    pub by_canton: bool,
+}
+
+/// Records of a table grouped by partition directory
+type Partitions<T> = BTreeMap<PathBuf, Vec<T>>;
+
+impl Datasets {
+    /// Write datasets on Parquet files in `dir`, return written files
+    pub fn write_parquet<P, S>(
+        &self,
+        dir: P,
+        meta: &Meta<S>,
+        options: &ParquetOptions,
+    ) -> Result<Vec<PathBuf>, Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+        S: fmt::Display,
+    {
+        let properties = WriterProperties::builder()
+            .set_key_value_metadata(Some(
+                meta.to_key_values()
+                    .into_iter()
+                    .map(|(key, value)| KeyValue::new(key, value))
+                    .collect(),
+            ))
+            .build();
+
+        let cantons = self.cantons.iter().collect::<Result<Vec<_>, _>>()?;
+        let districts = self.districts.iter().collect::<Result<Vec<_>, _>>()?;
+        let municipalities = self.municipalities.iter().collect::<Result<Vec<_>, _>>()?;
+        let abbreviations: HashMap<CantonId, String> = cantons
+            .iter()
+            .map(|canton| (canton.id, canton.abbreviation.clone()))
+            .collect();
+
+        let snapshots: Vec<Option<Date>> = if options.snapshots.is_empty() {
+            vec![None]
+        } else {
+            options.snapshots.iter().copied().map(Some).collect()
+        };
+        let partition = |snapshot: &Option<Date>, canton: &str| {
+            let mut path = PathBuf::new();
+            if let Some(date) = snapshot {
+                path.push(format!("snapshot={date}"));
+            }
+            if options.by_canton {
+                path.push(format!("canton={canton}"));
+            }
+            path
+        };
+        let valid = |record: &dyn Abolitable, snapshot: &Option<Date>| {
+            snapshot.is_none_or(|date| record.validity().contains(&date))
+        };
+
+        let mut canton_partitions: Partitions<_> = BTreeMap::new();
+        let mut district_partitions: Partitions<_> = BTreeMap::new();
+        let mut municipality_partitions: Partitions<_> = BTreeMap::new();
+        for snapshot in &snapshots {
+            for canton in &cantons {
+                canton_partitions
+                    .entry(partition(snapshot, &canton.abbreviation))
+                    .or_default()
+                    .push(canton.clone());
+            }
+            for district in districts.iter().filter(|d| valid(*d, snapshot)) {
+                let canton = abbreviations
+                    .get(&district.canton_id)
+                    .map_or_else(|| district.canton_id.to_string(), Clone::clone);
+                district_partitions
+                    .entry(partition(snapshot, &canton))
+                    .or_default()
+                    .push(district.clone());
+            }
+            for municipality in municipalities.iter().filter(|m| valid(*m, snapshot)) {
+                municipality_partitions
+                    .entry(partition(snapshot, &municipality.canton_abbreviation))
+                    .or_default()
+                    .push(municipality.clone());
+            }
+        }
+
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        files.extend(write_partitions(
+            &dir.join("cantons"),
+            canton_partitions,
+            &properties,
+        )?);
+        files.extend(write_partitions(
+            &dir.join("districts"),
+            district_partitions,
+            &properties,
+        )?);
+        files.extend(write_partitions(
+            &dir.join("municipalities"),
+            municipality_partitions,
+            &properties,
+        )?);
+        Ok(files)
+    }
+}
+
+fn write_partitions<T: ToRecordBatch>(
+    dir: &Path,
+    partitions: Partitions<T>,
+    properties: &WriterProperties,
+) -> Result<Vec<PathBuf>, Box<dyn error::Error>> {
+    let mut files = Vec::new();
+    for (partition, records) in partitions {
+        let dir = dir.join(partition);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("data.parquet");
+        let batch = T::to_record_batch(&records)?;
+        let mut writer = ArrowWriter::try_new(
+            File::create(&path)?,
+            batch.schema(),
+            Some(properties.clone()),
+        )?;
+        writer.write(&batch)?;
+        writer.close()?;
+        files.push(path);
+    }
+    Ok(files)
+}
@@ -0,0 +1,233 @@
+//! Conversion of records to Arrow [RecordBatch] (feature `arrow`)
+//!
+//! Dates are `Date32` columns, modes are dictionary columns with the variant
+//! name (like `PoliticalCommune`) and abolition columns are nullable.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! let batch = ds.municipalities.to_record_batch()?;
+//! println!("{:?}", batch.schema());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use ::arrow::array::{
+    ArrayRef, Date32Array, DictionaryArray, StringArray, UInt16Array, UInt32Array, UInt8Array,
+};
+use ::arrow::datatypes::{DataType, Date32Type, Field, Int8Type, Schema, SchemaRef};
+use ::arrow::record_batch::RecordBatch;
+use serde::Deserialize;
+
+use super::{Canton, Dataset, District, Municipality};
+use crate::Date;
+
+/// Records convertible to an Arrow [RecordBatch]
+pub trait ToRecordBatch: Sized {
+    /// Schema of record batch
+    fn schema() -> SchemaRef;
+
+    /// Build a record batch with records
+    fn to_record_batch(records: &[Self]) -> Result<RecordBatch, ::arrow::error::ArrowError>;
+}
+
+impl<T> Dataset<T>
+where
+    T: for<'de> Deserialize<'de> + ToRecordBatch,
+{
+    /// Convert all records to an Arrow [RecordBatch]
+    pub fn to_record_batch(&self) -> Result<RecordBatch, Box<dyn error::Error>> {
+        let records = self.iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(T::to_record_batch(&records)?)
+    }
+}
+
+fn date(date: &Date) -> i32 {
+    Date32Type::from_naive_date(*date)
+}
+
+fn mode<M: Debug>(mode: Option<M>) -> Option<String> {
+    mode.map(|mode| format!("{mode:?}"))
+}
+
+fn dictionary(name: &str, nullable: bool) -> Field {
+    Field::new(
+        name,
+        DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8)),
+        nullable,
+    )
+}
+
+fn modes<I, M>(modes: I) -> ArrayRef
+where
+    I: IntoIterator<Item = Option<M>>,
+    M: Debug,
+{
+    let names: Vec<_> = modes.into_iter().map(mode).collect();
+    let array: DictionaryArray<Int8Type> = names.iter().map(Option::as_deref).collect();
+    Arc::new(array)
+}
+
+impl ToRecordBatch for Canton {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::UInt8, false),
+            Field::new("abbreviation", DataType::Utf8, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("date_of_change", DataType::Date32, false),
+        ]))
+    }
+
+    fn to_record_batch(records: &[Self]) -> Result<RecordBatch, ::arrow::error::ArrowError> {
+        RecordBatch::try_new(
+            Self::schema(),
+            vec![
+                Arc::new(UInt8Array::from_iter_values(
+                    records.iter().map(|r| r.id.get()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.abbreviation),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.name),
+                )),
+                Arc::new(Date32Array::from_iter_values(
+                    records.iter().map(|r| date(&r.date_of_change)),
+                )),
+            ],
+        )
+    }
+}
+
+impl ToRecordBatch for District {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("hist_id", DataType::UInt32, false),
+            Field::new("canton_id", DataType::UInt8, false),
+            Field::new("id", DataType::UInt16, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("short_name", DataType::Utf8, false),
+            dictionary("entry_mode", false),
+            Field::new("admission_number", DataType::UInt16, false),
+            dictionary("admission_mode", false),
+            Field::new("admission_date", DataType::Date32, false),
+            Field::new("abolition_number", DataType::UInt16, true),
+            dictionary("abolition_mode", true),
+            Field::new("abolition_date", DataType::Date32, true),
+            Field::new("date_of_change", DataType::Date32, false),
+        ]))
+    }
+
+    fn to_record_batch(records: &[Self]) -> Result<RecordBatch, ::arrow::error::ArrowError> {
+        RecordBatch::try_new(
+            Self::schema(),
+            vec![
+                Arc::new(UInt32Array::from_iter_values(
+                    records.iter().map(|r| r.hist_id.get()),
+                )),
+                Arc::new(UInt8Array::from_iter_values(
+                    records.iter().map(|r| r.canton_id.get()),
+                )),
+                Arc::new(UInt16Array::from_iter_values(
+                    records.iter().map(|r| r.id.get()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.name),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.short_name),
+                )),
+                modes(records.iter().map(|r| Some(r.entry_mode))),
+                Arc::new(UInt16Array::from_iter_values(
+                    records.iter().map(|r| r.admission_number.get()),
+                )),
+                modes(records.iter().map(|r| Some(r.admission_mode))),
+                Arc::new(Date32Array::from_iter_values(
+                    records.iter().map(|r| date(&r.admission_date)),
+                )),
+                Arc::new(UInt16Array::from_iter(
+                    records.iter().map(|r| r.abolition_number.map(|n| n.get())),
+                )),
+                modes(records.iter().map(|r| r.abolition_mode)),
+                Arc::new(Date32Array::from_iter(
+                    records.iter().map(|r| r.abolition_date.as_ref().map(date)),
+                )),
+                Arc::new(Date32Array::from_iter_values(
+                    records.iter().map(|r| date(&r.date_of_change)),
+                )),
+            ],
+        )
+    }
+}
+
+impl ToRecordBatch for Municipality {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("hist_id", DataType::UInt32, false),
+            Field::new("district_hist_id", DataType::UInt32, false),
+            Field::new("canton_abbreviation", DataType::Utf8, false),
+            Field::new("id", DataType::UInt16, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("short_name", DataType::Utf8, false),
+            dictionary("entry_mode", false),
+            dictionary("status", false),
+            Field::new("admission_number", DataType::UInt16, false),
+            dictionary("admission_mode", false),
+            Field::new("admission_date", DataType::Date32, false),
+            Field::new("abolition_number", DataType::UInt16, true),
+            dictionary("abolition_mode", true),
+            Field::new("abolition_date", DataType::Date32, true),
+            Field::new("date_of_change", DataType::Date32, false),
+        ]))
+    }
+
+    fn to_record_batch(records: &[Self]) -> Result<RecordBatch, ::arrow::error::ArrowError> {
+        RecordBatch::try_new(
+            Self::schema(),
+            vec![
+                Arc::new(UInt32Array::from_iter_values(
+                    records.iter().map(|r| r.hist_id.get()),
+                )),
+                Arc::new(UInt32Array::from_iter_values(
+                    records.iter().map(|r| r.district_hist_id.get()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.canton_abbreviation),
+                )),
+                Arc::new(UInt16Array::from_iter_values(
+                    records.iter().map(|r| r.id.get()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.name),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    records.iter().map(|r| &r.short_name),
+                )),
+                modes(records.iter().map(|r| Some(r.entry_mode))),
+                modes(records.iter().map(|r| Some(r.status))),
+                Arc::new(UInt16Array::from_iter_values(
+                    records.iter().map(|r| r.admission_number.get()),
+                )),
+                modes(records.iter().map(|r| Some(r.admission_mode))),
+                Arc::new(Date32Array::from_iter_values(
+                    records.iter().map(|r| date(&r.admission_date)),
+                )),
+                Arc::new(UInt16Array::from_iter(
+                    records.iter().map(|r| r.abolition_number.map(|n| n.get())),
+                )),
+                modes(records.iter().map(|r| r.abolition_mode)),
+                Arc::new(Date32Array::from_iter(
+                    records.iter().map(|r| r.abolition_date.as_ref().map(date)),
+                )),
+                Arc::new(Date32Array::from_iter_values(
+                    records.iter().map(|r| date(&r.date_of_change)),
+                )),
+            ],
+        )
+    }
+}
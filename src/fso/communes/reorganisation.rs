@@ -0,0 +1,135 @@
+//! Reorganisations of districts
+//!
+//! A reorganisation is a district mutation with
+//! [AbolitionMode::DistrictNameChange], [AbolitionMode::AttachmentToAnother]
+//! or [AbolitionMode::Radiation] (like Bern 2010, Vaud 2008 or St. Gallen
+//! 2003), with the composition of districts before and after.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! for (canton, reorganisations) in ds.district_reorganisations()? {
+//!     for reorganisation in reorganisations {
+//!         println!("{canton} {}", reorganisation.date);
+//!         for composition in &reorganisation.before {
+//!             println!(
+//!                 " - {} ({} municipalities)",
+//!                 composition.district.name,
+//!                 composition.municipalities.len()
+//!             );
+//!         }
+//!         for composition in &reorganisation.after {
+//!             println!(
+//!                 " + {} ({} municipalities)",
+//!                 composition.district.name,
+//!                 composition.municipalities.len()
+//!             );
+//!         }
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+
+use super::{Abolitable, AbolitionMode, CantonId, Datasets, District, Municipality, MutationId};
+use crate::Date;
+
+/// A district with its municipalities at a date
+#[derive(Clone, Debug)]
+pub struct Composition {
+    /// District record
+    pub district: District,
+    /// Municipalities of district
+    pub municipalities: Vec<Municipality>,
+}
+
+/// A reorganisation of districts of a canton
+#[derive(Clone, Debug)]
+pub struct Reorganisation {
+    /// Canton of districts
+    pub canton_id: CantonId,
+    /// Identifier of mutation
+    pub number: MutationId,
+    /// Date of reorganisation (first day of new districts)
+    pub date: Date,
+    /// Abolished districts with municipalities on their last day
+    pub before: Vec<Composition>,
+    /// Admitted districts with municipalities on their first day
+    pub after: Vec<Composition>,
+}
+
+/// Indicator if an abolition is a reorganisation
+fn is_reorganisation(mode: AbolitionMode) -> bool {
+    matches!(
+        mode,
+        AbolitionMode::DistrictNameChange
+            | AbolitionMode::AttachmentToAnother
+            | AbolitionMode::Radiation
+    )
+}
+
+impl Datasets {
+    /// List reorganisations of districts per canton (sorted by date)
+    pub fn district_reorganisations(
+        &self,
+    ) -> Result<BTreeMap<CantonId, Vec<Reorganisation>>, csv::Error> {
+        let districts = self.districts.iter().collect::<Result<Vec<_>, _>>()?;
+        let municipalities = self.municipalities.iter().collect::<Result<Vec<_>, _>>()?;
+        let composition = |district: &District, date: Date| Composition {
+            district: district.clone(),
+            municipalities: municipalities
+                .iter()
+                .filter(|m| m.district_hist_id == district.hist_id)
+                .filter(|m| m.validity().contains(&date))
+                .cloned()
+                .collect(),
+        };
+
+        let mut reorganisations: BTreeMap<(CantonId, MutationId), Reorganisation> = BTreeMap::new();
+        for district in &districts {
+            let (Some(number), Some(mode), Some(date)) = (
+                district.abolition_number,
+                district.abolition_mode,
+                district.abolition_date,
+            ) else {
+                continue;
+            };
+            if !is_reorganisation(mode) {
+                continue;
+            }
+            reorganisations
+                .entry((district.canton_id, number))
+                .or_insert_with(|| Reorganisation {
+                    canton_id: district.canton_id,
+                    number,
+                    date: date.succ_opt().unwrap_or(date),
+                    before: Vec::new(),
+                    after: Vec::new(),
+                })
+                .before
+                .push(composition(district, date));
+        }
+        for district in &districts {
+            if let Some(reorganisation) =
+                reorganisations.get_mut(&(district.canton_id, district.admission_number))
+            {
+                reorganisation.date = district.admission_date;
+                reorganisation
+                    .after
+                    .push(composition(district, district.admission_date));
+            }
+        }
+
+        let mut cantons: BTreeMap<CantonId, Vec<Reorganisation>> = BTreeMap::new();
+        for ((canton_id, _), reorganisation) in reorganisations {
+            cantons.entry(canton_id).or_default().push(reorganisation);
+        }
+        for reorganisations in cantons.values_mut() {
+            reorganisations.sort_by_key(|r| (r.date, r.number));
+        }
+        Ok(cantons)
+    }
+}
@@ -0,0 +1,272 @@
+//! Export datasets on SQLite database (feature `sqlite`)
+//!
+//! Tables:
+//!
+//! - `cantons`, `districts` and `municipalities`: records, with foreign keys on
+//!   `canton_id` and `district_hist_id`
+//! - `mutation_events`: one row per admission or abolition of a district or a
+//!   municipality
+//! - `meta`: meta information about data (see [Meta::to_key_values])
+//!
+//! Views `current_districts` and `current_municipalities` contain the actual
+//! state (not abolished records), with names of canton and district. Dates
+//! are on ISO format (`YYYY-MM-DD`), modes are their numeric code.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! ds.export_sqlite("communes.sqlite")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [Meta::to_key_values]: crate::tools::meta::Meta::to_key_values
+
+use std::collections::HashMap;
+use std::error;
+use std::path::Path;
+
+use rusqlite::{params, Connection, Transaction};
+
+use super::{Abolitable, CantonId, Datasets};
+use crate::tools::dataset::Datastore;
+
+const SCHEMA: &str = "
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE cantons (
+    id INTEGER PRIMARY KEY,
+    abbreviation TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    date_of_change TEXT NOT NULL
+);
+
+CREATE TABLE districts (
+    hist_id INTEGER PRIMARY KEY,
+    canton_id INTEGER NOT NULL REFERENCES cantons(id),
+    id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    short_name TEXT NOT NULL,
+    entry_mode INTEGER NOT NULL,
+    admission_number INTEGER NOT NULL,
+    admission_mode INTEGER NOT NULL,
+    admission_date TEXT NOT NULL,
+    abolition_number INTEGER,
+    abolition_mode INTEGER,
+    abolition_date TEXT,
+    date_of_change TEXT NOT NULL
+);
+CREATE INDEX districts_canton_id ON districts(canton_id);
+CREATE INDEX districts_id ON districts(id);
+
+CREATE TABLE municipalities (
+    hist_id INTEGER PRIMARY KEY,
+    district_hist_id INTEGER NOT NULL REFERENCES districts(hist_id),
+    canton_id INTEGER REFERENCES cantons(id),
+    canton_abbreviation TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    short_name TEXT NOT NULL,
+    entry_mode INTEGER NOT NULL,
+    status INTEGER NOT NULL,
+    admission_number INTEGER NOT NULL,
+    admission_mode INTEGER NOT NULL,
+    admission_date TEXT NOT NULL,
+    abolition_number INTEGER,
+    abolition_mode INTEGER,
+    abolition_date TEXT,
+    date_of_change TEXT NOT NULL
+);
+CREATE INDEX municipalities_district_hist_id ON municipalities(district_hist_id);
+CREATE INDEX municipalities_canton_id ON municipalities(canton_id);
+CREATE INDEX municipalities_id ON municipalities(id);
+CREATE INDEX municipalities_name ON municipalities(name);
+
+CREATE TABLE mutation_events (
+    number INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    event TEXT NOT NULL CHECK (event IN ('admission', 'abolition')),
+    mode INTEGER NOT NULL,
+    district_hist_id INTEGER REFERENCES districts(hist_id),
+    municipality_hist_id INTEGER REFERENCES municipalities(hist_id),
+    CHECK ((district_hist_id IS NULL) != (municipality_hist_id IS NULL))
+);
+CREATE INDEX mutation_events_number ON mutation_events(number);
+CREATE INDEX mutation_events_date ON mutation_events(date);
+CREATE INDEX mutation_events_district_hist_id ON mutation_events(district_hist_id);
+CREATE INDEX mutation_events_municipality_hist_id ON mutation_events(municipality_hist_id);
+
+CREATE VIEW current_districts AS
+SELECT d.*, c.abbreviation AS canton_abbreviation, c.name AS canton_name
+FROM districts d
+JOIN cantons c ON c.id = d.canton_id
+WHERE d.abolition_number IS NULL;
+
+CREATE VIEW current_municipalities AS
+SELECT m.*, d.id AS district_id, d.name AS district_name, c.name AS canton_name
+FROM municipalities m
+JOIN districts d ON d.hist_id = m.district_hist_id
+LEFT JOIN cantons c ON c.id = m.canton_id
+WHERE m.abolition_number IS NULL;
+";
+
+impl Datasets {
+    /// Write datasets on a new SQLite database
+    ///
+    /// The database at `path` must not already contain the tables.
+    pub fn export_sqlite<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn error::Error>> {
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SCHEMA)?;
+        self.insert_meta(&transaction)?;
+        let cantons = self.insert_cantons(&transaction)?;
+        self.insert_districts(&transaction)?;
+        self.insert_municipalities(&transaction, &cantons)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_meta(&self, transaction: &Transaction) -> Result<(), Box<dyn error::Error>> {
+        let mut insert = transaction.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+        for (key, value) in super::datastore().meta().to_key_values() {
+            insert.execute(params![key, value])?;
+        }
+        Ok(())
+    }
+
+    fn insert_cantons(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<HashMap<String, CantonId>, Box<dyn error::Error>> {
+        let mut insert = transaction.prepare(
+            "INSERT INTO cantons (id, abbreviation, name, date_of_change)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut cantons = HashMap::new();
+        for canton in &self.cantons {
+            let canton = canton?;
+            insert.execute(params![
+                canton.id.get(),
+                canton.abbreviation,
+                canton.name,
+                canton.date_of_change.to_string(),
+            ])?;
+            cantons.insert(canton.abbreviation, canton.id);
+        }
+        Ok(cantons)
+    }
+
+    fn insert_districts(&self, transaction: &Transaction) -> Result<(), Box<dyn error::Error>> {
+        let mut insert = transaction.prepare(
+            "INSERT INTO districts (
+                hist_id, canton_id, id, name, short_name, entry_mode,
+                admission_number, admission_mode, admission_date,
+                abolition_number, abolition_mode, abolition_date, date_of_change
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        )?;
+        let mut event = transaction.prepare(
+            "INSERT INTO mutation_events (number, date, event, mode, district_hist_id)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for district in &self.districts {
+            let district = district?;
+            insert.execute(params![
+                district.hist_id.get(),
+                district.canton_id.get(),
+                district.id.get(),
+                district.name,
+                district.short_name,
+                district.entry_mode as u8,
+                district.admission_number.get(),
+                district.admission_mode as u8,
+                district.admission_date.to_string(),
+                district.abolition_number.map(|n| n.get()),
+                district.abolition_mode.map(|m| m as u8),
+                district.abolition_date.map(|d| d.to_string()),
+                district.date_of_change.to_string(),
+            ])?;
+            let admission = district.admission();
+            event.execute(params![
+                admission.number.get(),
+                admission.date.to_string(),
+                "admission",
+                admission.mode as u8,
+                district.hist_id.get(),
+            ])?;
+            if let Some(abolition) = district.abolition() {
+                event.execute(params![
+                    abolition.number.get(),
+                    abolition.date.to_string(),
+                    "abolition",
+                    abolition.mode as u8,
+                    district.hist_id.get(),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_municipalities(
+        &self,
+        transaction: &Transaction,
+        cantons: &HashMap<String, CantonId>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let mut insert = transaction.prepare(
+            "INSERT INTO municipalities (
+                hist_id, district_hist_id, canton_id, canton_abbreviation, id, name,
+                short_name, entry_mode, status, admission_number, admission_mode,
+                admission_date, abolition_number, abolition_mode, abolition_date,
+                date_of_change
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        )?;
+        let mut event = transaction.prepare(
+            "INSERT INTO mutation_events (number, date, event, mode, municipality_hist_id)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for municipality in &self.municipalities {
+            let municipality = municipality?;
+            insert.execute(params![
+                municipality.hist_id.get(),
+                municipality.district_hist_id.get(),
+                cantons
+                    .get(&municipality.canton_abbreviation)
+                    .map(|id| id.get()),
+                municipality.canton_abbreviation,
+                municipality.id.get(),
+                municipality.name,
+                municipality.short_name,
+                municipality.entry_mode as u8,
+                municipality.status as u8,
+                municipality.admission_number.get(),
+                municipality.admission_mode as u8,
+                municipality.admission_date.to_string(),
+                municipality.abolition_number.map(|n| n.get()),
+                municipality.abolition_mode.map(|m| m as u8),
+                municipality.abolition_date.map(|d| d.to_string()),
+                municipality.date_of_change.to_string(),
+            ])?;
+            let admission = municipality.admission();
+            event.execute(params![
+                admission.number.get(),
+                admission.date.to_string(),
+                "admission",
+                admission.mode as u8,
+                municipality.hist_id.get(),
+            ])?;
+            if let Some(abolition) = municipality.abolition() {
+                event.execute(params![
+                    abolition.number.get(),
+                    abolition.date.to_string(),
+                    "abolition",
+                    abolition.mode as u8,
+                    municipality.hist_id.get(),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+}
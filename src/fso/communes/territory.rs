@@ -0,0 +1,164 @@
+//! Stable identity of municipality territory
+//!
+//! Some mutations are purely administrative, the territory doesn't change but
+//! a new record (with a new [MunicipalityHistId], sometimes a new
+//! [super::MunicipalityId]) is created:
+//!
+//! - [AdmissionMode::DistrictNameChange] / [AbolitionMode::DistrictNameChange]
+//! - [AdmissionMode::MunicipalityNameChange] /
+//!   [AbolitionMode::MunicipalityNameChange]
+//! - [AdmissionMode::AttachmentToAnother] /
+//!   [AbolitionMode::AttachmentToAnother]
+//! - [AdmissionMode::FormalRenumbering] / [AbolitionMode::FormalRenumbering]
+//!
+//! [TerritoryId] follows records across these mutations and changes only when
+//! the territory changes.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//!
+//! let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+//! let territories = ds.municipalities.territories()?;
+//! for municipality in ds.municipalities.actual() {
+//!     let municipality = municipality?;
+//!     let territory = territories.get(municipality.hist_id).unwrap();
+//!     println!("{} {territory}", municipality.name);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{AbolitionMode, AdmissionMode, Dataset, Municipality, MunicipalityHistId, MutationId};
+
+/// Stable identifier of a municipality territory
+///
+/// This is the [MunicipalityHistId] of the first record with this territory.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[serde(transparent)]
+pub struct TerritoryId(pub MunicipalityHistId);
+impl fmt::Display for TerritoryId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(fmt)
+    }
+}
+
+/// Indicator if an admission keep the territory of predecessor
+fn is_administrative_admission(mode: AdmissionMode) -> bool {
+    matches!(
+        mode,
+        AdmissionMode::DistrictNameChange
+            | AdmissionMode::MunicipalityNameChange
+            | AdmissionMode::AttachmentToAnother
+            | AdmissionMode::FormalRenumbering
+    )
+}
+
+/// Indicator if an abolition keep the territory for successor
+fn is_administrative_abolition(mode: AbolitionMode) -> bool {
+    matches!(
+        mode,
+        AbolitionMode::DistrictNameChange
+            | AbolitionMode::MunicipalityNameChange
+            | AbolitionMode::AttachmentToAnother
+            | AbolitionMode::FormalRenumbering
+    )
+}
+
+/// Territory of each municipality record
+///
+/// Build with [Dataset::territories]
+pub struct Territories {
+    territories: HashMap<MunicipalityHistId, TerritoryId>,
+    predecessors: HashMap<MunicipalityHistId, MunicipalityHistId>,
+}
+impl Territories {
+    /// Territory of a municipality record
+    pub fn get(&self, hist_id: MunicipalityHistId) -> Option<TerritoryId> {
+        self.territories.get(&hist_id).copied()
+    }
+
+    /// Record replaced by this one with same territory
+    pub fn predecessor(&self, hist_id: MunicipalityHistId) -> Option<MunicipalityHistId> {
+        self.predecessors.get(&hist_id).copied()
+    }
+
+    /// All records of a territory (sorted)
+    pub fn records(&self, territory: TerritoryId) -> Vec<MunicipalityHistId> {
+        let mut records: Vec<_> = self
+            .territories
+            .iter()
+            .filter(|(_, id)| **id == territory)
+            .map(|(hist_id, _)| *hist_id)
+            .collect();
+        records.sort();
+        records
+    }
+}
+
+impl Dataset<Municipality> {
+    /// Compute the territory of each record
+    pub fn territories(&self) -> Result<Territories, csv::Error> {
+        let mut abolished: HashMap<MutationId, Vec<Municipality>> = HashMap::new();
+        let mut admitted: HashMap<MutationId, Vec<Municipality>> = HashMap::new();
+        let mut hist_ids = Vec::new();
+        for municipality in self {
+            let municipality = municipality?;
+            hist_ids.push(municipality.hist_id);
+            if is_administrative_admission(municipality.admission_mode) {
+                admitted
+                    .entry(municipality.admission_number)
+                    .or_default()
+                    .push(municipality.clone());
+            }
+            match (municipality.abolition_number, municipality.abolition_mode) {
+                (Some(number), Some(mode)) if is_administrative_abolition(mode) => {
+                    abolished.entry(number).or_default().push(municipality);
+                }
+                _ => {}
+            }
+        }
+
+        let mut predecessors = HashMap::new();
+        for (number, mut olds) in abolished {
+            let Some(mut news) = admitted.remove(&number) else {
+                continue;
+            };
+            // same municipality id, then same name, then the single remaining
+            for same in [
+                |old: &Municipality, new: &Municipality| old.id == new.id,
+                |old: &Municipality, new: &Municipality| old.name == new.name,
+            ] {
+                olds.retain(|old| match news.iter().position(|new| same(old, new)) {
+                    Some(i) => {
+                        predecessors.insert(news.swap_remove(i).hist_id, old.hist_id);
+                        false
+                    }
+                    None => true,
+                });
+            }
+            if let ([old], [new]) = (olds.as_slice(), news.as_slice()) {
+                predecessors.insert(new.hist_id, old.hist_id);
+            }
+        }
+
+        let territories = hist_ids
+            .into_iter()
+            .map(|hist_id| {
+                let mut root = hist_id;
+                while let Some(predecessor) = predecessors.get(&root) {
+                    root = *predecessor;
+                }
+                (hist_id, TerritoryId(root))
+            })
+            .collect();
+        Ok(Territories {
+            territories,
+            predecessors,
+        })
+    }
+}
@@ -2,6 +2,7 @@
 //! - BFS - Bundesamt für Statistik
 //! - OFS - Office fédéral de la statistique
 //! - UST - Ufficia federale di statistica
+pub mod boundaries;
 pub mod communes;
 //pub mod communes_historical;
 
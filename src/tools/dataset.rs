@@ -1,7 +1,11 @@
 //! Traits represent standard interface to access data
 
 use std::error;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
+use crate::tools::downloader::CachePolicy;
 use crate::tools::meta::Meta;
 use crate::tools::Downloader;
 
@@ -18,9 +22,210 @@ pub trait Datastore<S> {
         None
     }
 
+    /// Url of files downloaded by [Self::load]
+    ///
+    /// Used by [Shared] for detect a newer file in cache, if empty, the store
+    /// is never reloaded.
+    fn urls(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Download data with downloader and return the stor for access to this
     /// data
     fn load<D>(&self, downloader: D) -> Result<Self::Store, Box<dyn error::Error>>
     where
         D: Downloader;
+
+    /// Same as [Self::load], with a [CachePolicy] (like offline only)
+    fn load_with_policy<D>(
+        &self,
+        downloader: D,
+        policy: CachePolicy,
+    ) -> Result<Self::Store, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        self.load(downloader.with_policy(policy))
+    }
+}
+
+/// A loaded store with the version of cached files used to build it
+struct Snapshot<T> {
+    store: Arc<T>,
+    versions: Vec<SystemTime>,
+}
+
+/// Thread-safe store, cheaply clonable, with hot reload
+///
+/// All clones share the same data. [Shared::get] return a snapshot of store,
+/// this snapshot keep unchanged during a [Shared::reload], so readers always
+/// have a consistent view of data.
+///
+/// ```no_run
+/// use swissdata::fso::communes;
+/// use swissdata::tools::dataset::Shared;
+///
+/// let client = reqwest::blocking::Client::new();
+/// let shared = Shared::new(communes::datastore(), &client)?;
+/// let worker = shared.clone();
+/// std::thread::spawn(move || {
+///     let ds = worker.get();
+///     println!("{} cantons", ds.cantons.iter().count());
+/// });
+/// // later, when cache can be expired
+/// shared.reload(&client)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Shared<DS, S>
+where
+    DS: Datastore<S>,
+{
+    datastore: Arc<DS>,
+    current: Arc<RwLock<Snapshot<DS::Store>>>,
+    phantom: PhantomData<fn() -> S>,
+}
+impl<DS, S> Shared<DS, S>
+where
+    DS: Datastore<S>,
+{
+    /// Load data of datastore with downloader
+    pub fn new<D>(datastore: DS, downloader: D) -> Result<Self, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        let versions = Self::versions(&datastore, &downloader)?;
+        let store = Arc::new(datastore.load(downloader)?);
+        Ok(Self {
+            datastore: Arc::new(datastore),
+            current: Arc::new(RwLock::new(Snapshot { store, versions })),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Get the datastore used for load data
+    pub fn datastore(&self) -> &DS {
+        &self.datastore
+    }
+
+    /// Get a snapshot of actual store
+    pub fn get(&self) -> Arc<DS::Store> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .store
+            .clone()
+    }
+
+    /// Reload data if a newer file is valid in downloader cache
+    ///
+    /// Files are downloaded if cache isn't valid. Return `true` if store was
+    /// replaced, the store is never replaced by a store built on older files
+    /// (like a slow reload finishing after a faster one). Snapshots already
+    /// returned by [Shared::get] keep the old data.
+    pub fn reload<D>(&self, downloader: D) -> Result<bool, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        let versions = Self::versions(&self.datastore, &downloader)?;
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        if !is_newer(&versions, &current.versions) {
+            return Ok(false);
+        }
+        drop(current);
+        let store = Arc::new(self.datastore.load(downloader)?);
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        if !is_newer(&versions, &current.versions) {
+            // an other thread has already reload this version or a newer one
+            return Ok(false);
+        }
+        *current = Snapshot { store, versions };
+        Ok(true)
+    }
+
+    fn versions<D>(datastore: &DS, downloader: &D) -> Result<Vec<SystemTime>, Box<dyn error::Error>>
+    where
+        D: Downloader,
+    {
+        datastore
+            .urls()
+            .iter()
+            .map(|url| Ok(downloader.cache_get(url)?.metadata()?.modified()?))
+            .collect()
+    }
+}
+
+/// Check if all files of `versions` are at least as recent as in `current`,
+/// and one is more recent
+fn is_newer(versions: &[SystemTime], current: &[SystemTime]) -> bool {
+    versions != current
+        && versions.len() == current.len()
+        && versions
+            .iter()
+            .zip(current)
+            .all(|(version, current)| version >= current)
+}
+
+impl<DS, S> Clone for Shared<DS, S>
+where
+    DS: Datastore<S>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            datastore: self.datastore.clone(),
+            current: self.current.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::fso::communes;
+    use crate::tools::downloader::mock::Mock;
+    use crate::tools::internal::testing::{communes_zip, set_modified};
+
+    // stores are shared between threads
+    const _: fn() = || {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<communes::Datasets>();
+        send_sync::<Shared<communes::Datastore, &'static str>>();
+    };
+
+    #[test]
+    fn reload_keeps_snapshots() {
+        let url = communes::datastore().urls().remove(0);
+        let mock = Mock::new().with_content(url.clone(), communes_zip());
+        let shared = Shared::new(communes::datastore(), &mock).unwrap();
+        let snapshot = shared.get();
+        assert!(!shared.reload(&mock).unwrap());
+        assert!(Arc::ptr_eq(&snapshot, &shared.get()));
+
+        // a newer file is downloaded
+        let path = (&mock).cache_path(&url).unwrap();
+        set_modified(&path, SystemTime::now() - Duration::from_secs(60));
+        let refresh = (&mock).with_policy(CachePolicy::ForceRefresh);
+        assert!(shared.reload(refresh).unwrap());
+        let reloaded = shared.get();
+        assert!(!Arc::ptr_eq(&snapshot, &reloaded));
+        assert_eq!(snapshot.municipalities.iter().count(), 1);
+
+        // an older file never replace the store
+        set_modified(&path, UNIX_EPOCH);
+        let offline = (&mock).with_policy(CachePolicy::OfflineOnly);
+        assert!(!shared.reload(offline).unwrap());
+        assert!(Arc::ptr_eq(&reloaded, &shared.get()));
+    }
+
+    #[test]
+    fn newer_versions() {
+        let old = UNIX_EPOCH;
+        let new = UNIX_EPOCH + Duration::from_secs(1);
+        assert!(is_newer(&[new, old], &[old, old]));
+        assert!(!is_newer(&[old, old], &[old, old]));
+        assert!(!is_newer(&[old, new], &[new, old]));
+        assert!(!is_newer(&[old], &[new]));
+    }
 }
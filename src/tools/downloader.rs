@@ -1,14 +1,276 @@
 //! Trait for implement downloader compatible with all dataset
 
+use std::collections::BTreeMap;
 use std::error;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, SystemTime};
 
-use dirs;
 use reqwest;
-use urlencoding;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::fso::asset::AssetId;
+use crate::i_serde;
+
+pub mod cache;
+pub mod cassette;
+pub mod mock;
+pub mod progress;
+pub mod retry;
+
+use cache::Cache;
+use cassette::{Cassette, CassetteMode};
+use progress::{Event, Progress, ProgressReader, WithProgress};
+use retry::{Retry, RetryConfig};
+
+/// Validators of a response, sent back on a conditional request
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    /// `ETag` header, sent on `If-None-Match`
+    pub etag: Option<String>,
+    /// `Last-Modified` header, sent on `If-Modified-Since`
+    pub last_modified: Option<String>,
+}
+impl Validators {
+    /// Check if there is no validator
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Response of a conditional request
+pub enum Conditional<R> {
+    /// Content isn't modified (HTTP 304), cache is still valid
+    NotModified,
+    /// New content, with its validators
+    Modified(R, Validators),
+}
+
+/// Information stored beside a cached file (`<file>.json`)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CacheInfo {
+    /// Url of content
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Time of download (RFC 3339)
+    #[serde(default, with = "i_serde::option_system_time_rfc3339")]
+    pub fetched: Option<SystemTime>,
+    /// Headers of the response
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Id of FSO asset, if the content is an asset (see
+    /// [crate::fso::asset::Asset])
+    #[serde(default)]
+    pub asset: Option<AssetId>,
+    /// Validators of the response
+    #[serde(default)]
+    pub validators: Validators,
+    /// Last time the server confirm the cached content is up to date (RFC
+    /// 3339)
+    #[serde(default, with = "i_serde::option_system_time_rfc3339")]
+    pub revalidated: Option<SystemTime>,
+    /// SHA-256 of the content (lowercase hexadecimal)
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+impl CacheInfo {
+    /// Path of information file for a cached file
+    pub fn path<P: AsRef<Path>>(cache: P) -> PathBuf {
+        with_suffix(cache, ".json")
+    }
+
+    /// Read information of a cached file, `None` if missing or invalid
+    pub fn load<P: AsRef<Path>>(cache: P) -> Option<Self> {
+        serde_json::from_slice(&fs::read(Self::path(cache)).ok()?).ok()
+    }
+
+    /// Write information of a cached file
+    pub fn save<P: AsRef<Path>>(&self, cache: P) -> Result<(), Box<dyn error::Error>> {
+        let content = serde_json::to_vec_pretty(self)?;
+        write_atomic(Self::path(cache), |file| {
+            io::Write::write_all(file, &content)
+        })
+    }
+
+    /// Modify information of a cached file under the lock of file
+    pub fn update<P, F>(cache: P, update: F) -> Result<(), Box<dyn error::Error>>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Self),
+    {
+        let cache = cache.as_ref();
+        let _lock = CacheLock::acquire(cache)?;
+        let mut info = Self::load(cache).unwrap_or_default();
+        update(&mut info);
+        info.save(cache)
+    }
+}
+
+/// Content of a cached file doesn't match the expected SHA-256
+#[derive(Debug)]
+pub struct IntegrityError {
+    /// Url of content
+    pub url: String,
+    /// Path of cached file
+    pub path: PathBuf,
+    /// Expected SHA-256 (recorded on download or pinned)
+    pub expected: String,
+    /// SHA-256 of cached file
+    pub actual: String,
+}
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "SHA-256 of {} ({}) is {}, expected {}",
+            self.url,
+            self.path.display(),
+            self.actual,
+            self.expected
+        )
+    }
+}
+impl error::Error for IntegrityError {}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// SHA-256 of a file (lowercase hexadecimal)
+pub fn file_sha256<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Reader computing SHA-256 of read content
+struct HashReader<R> {
+    inner: R,
+    hasher: Sha256,
+    /// A read failed (the content is incomplete)
+    failed: bool,
+}
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf).inspect_err(|_| self.failed = true)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Read of content fail during a download (after the response)
+#[derive(Debug)]
+struct TransferError {
+    url: String,
+    error: io::Error,
+}
+impl fmt::Display for TransferError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Transfer of {} failed: {}", self.url, self.error)
+    }
+}
+impl error::Error for TransferError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+fn with_suffix<P: AsRef<Path>>(path: P, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path.as_ref());
+    path.push(suffix);
+    path.into()
+}
+
+/// Counter of temporary files of this process
+static TEMPORARIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Write a file through a temporary file renamed at the end, so a file is
+/// never partially written (the rename is atomic on the same filesystem)
+fn write_atomic<P, F>(path: P, write: F) -> Result<(), Box<dyn error::Error>>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let temporary = write_temporary(path.as_ref(), write)?;
+    rename_temporary(&temporary, path.as_ref())
+}
+
+/// Write a temporary file beside `path`, to rename on `path` when complete
+/// (see [rename_temporary])
+fn write_temporary<F>(path: &Path, write: F) -> Result<PathBuf, Box<dyn error::Error>>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    // unique by process and by call, for writes of other threads
+    let id = TEMPORARIES.fetch_add(1, Ordering::Relaxed);
+    let temporary = with_suffix(path, &format!(".{}.{id}.tmp", std::process::id()));
+    let mut file = File::create(&temporary)?;
+    if let Err(e) = write(&mut file).and_then(|_| file.sync_all()) {
+        drop(file);
+        let _ = fs::remove_file(&temporary);
+        return Err(e.into());
+    }
+    drop(file);
+    Ok(temporary)
+}
+
+/// Rename a temporary file on `path`, the temporary file is removed on error
+fn rename_temporary(temporary: &Path, path: &Path) -> Result<(), Box<dyn error::Error>> {
+    if let Err(e) = fs::rename(temporary, path) {
+        let _ = fs::remove_file(temporary);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Remove a file, without error if it doesn't exist
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Advisory lock on a cached file (on `<file>.lock`), shared between
+/// processes, released on drop
+struct CacheLock {
+    _file: File,
+}
+impl CacheLock {
+    /// Wait for an exclusive lock
+    fn acquire<P: AsRef<Path>>(cache: P) -> io::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(with_suffix(cache, ".lock"))?;
+        file.lock()?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Usage of cache by [Downloader::cache_get]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Use cache during [Downloader::default_validity], download (or
+    /// revalidate) after
+    #[default]
+    Default,
+    /// Never use network, use cache even if expired, fail if url isn't cached
+    OfflineOnly,
+    /// Always download, even if cache is valid
+    ForceRefresh,
+    /// Same as [CachePolicy::Default], but use expired cache if download fail
+    /// (like an outage of server), reported by [progress::Event::Stale]
+    StaleIfError,
+}
 
 /// Downloader trait for use a custom lib for download and cache data
 pub trait Downloader {
@@ -23,25 +285,390 @@ pub trait Downloader {
     /// direct download url
     fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError>;
 
+    /// Download url only if modified since the response with `validators`
+    /// (`If-None-Match` and `If-Modified-Since` headers)
+    ///
+    /// Default implementation doesn't support conditional request and always
+    /// download with [Self::http_get].
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        let _ = validators;
+        Ok(Conditional::Modified(
+            self.http_get(url)?,
+            Validators::default(),
+        ))
+    }
+
+    /// Size of content (`Content-Length`) of a response if known, reported
+    /// by [progress::Event::Started]
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        let _ = read;
+        None
+    }
+
+    /// Headers of a response, recorded in [CacheInfo]
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        let _ = read;
+        BTreeMap::new()
+    }
+
+    /// Check if a download error is transient (like a timeout or a server
+    /// error), so the download can be retried (see [Self::with_retry])
+    ///
+    /// Default implementation consider all errors as transient.
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        let _ = error;
+        true
+    }
+
+    /// Check if an error on read of content is transient, so the download
+    /// can be retried (see [Self::with_retry])
+    ///
+    /// Default implementation consider timeouts, interrupted connections and
+    /// unexpected end of content as transient.
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        retry::is_transient(error)
+    }
+
+    /// Configuration of retries used by [Self::cache_get] for read of
+    /// content, see [Self::with_retry]
+    fn retry_config(&self) -> Option<&RetryConfig> {
+        None
+    }
+
     /// check if a cache is valid
+    ///
+    /// The validity start at the last modification of file, or at the last
+    /// revalidation (see [CacheInfo]).
     fn is_valid<P: AsRef<Path>>(&self, path: P) -> Result<bool, Box<dyn error::Error>> {
-        Ok(path.as_ref().is_file()
-            && path.as_ref().metadata()?.modified()? + self.default_validity() > SystemTime::now())
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Ok(false);
+        }
+        let modified = path.metadata()?.modified()?;
+        let checked = CacheInfo::load(path)
+            .and_then(|info| info.revalidated)
+            .map_or(modified, |revalidated| revalidated.max(modified));
+        // a too long validity never expire
+        Ok(checked
+            .checked_add(self.default_validity())
+            .is_none_or(|expiration| expiration > SystemTime::now()))
+    }
+
+    /// Policy used by [Self::cache_get] and [Self::cache_get_verified]
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::default()
+    }
+
+    /// Use this downloader with an other [CachePolicy]
+    ///
+    /// ```no_run
+    /// use swissdata::fso::communes;
+    /// use swissdata::tools::dataset::Datastore;
+    /// use swissdata::tools::downloader::{CachePolicy, Downloader};
+    ///
+    /// let client = reqwest::blocking::Client::new();
+    /// let ds = communes::datastore().load((&client).with_policy(CachePolicy::StaleIfError))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn with_policy(self, policy: CachePolicy) -> WithPolicy<Self>
+    where
+        Self: Sized,
+    {
+        WithPolicy {
+            downloader: self,
+            policy,
+        }
+    }
+
+    /// Observer of downloads and usage of cache, default observer ignore
+    /// all events (use [progress::Warnings] to print warnings)
+    fn progress(&self) -> &dyn Progress {
+        &()
+    }
+
+    /// Use this downloader with an other [Progress] observer
+    fn with_progress<P: Progress>(self, progress: P) -> WithProgress<Self, P>
+    where
+        Self: Sized,
+    {
+        WithProgress {
+            downloader: self,
+            progress,
+        }
+    }
+
+    /// Use this downloader with retries of transient failures
+    fn with_retry(self, config: RetryConfig) -> Retry<Self>
+    where
+        Self: Sized,
+    {
+        Retry {
+            downloader: self,
+            config,
+        }
+    }
+
+    /// Use this downloader through a cassette recording or replaying
+    /// responses in `dir` (see [cassette])
+    fn with_cassette<P: Into<PathBuf>>(self, dir: P, mode: CassetteMode) -> Cassette<Self>
+    where
+        Self: Sized,
+    {
+        Cassette {
+            downloader: self,
+            cassette: Cache::new(dir),
+            mode,
+        }
     }
 
     /// Get path with valid data for url (download if required)
+    ///
+    /// An expired cache is revalidated with a conditional request if the
+    /// previous response had validators. The download is written on a
+    /// temporary file renamed at the end, under a lock shared by all
+    /// processes using the same cache.
+    ///
+    /// The SHA-256 of content is recorded on download and checked before
+    /// each use, an [IntegrityError] is returned if it doesn't match.
     fn cache_get(&self, url: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.cache_get_with_policy(url, self.cache_policy(), None)
+    }
+
+    /// Same as [Self::cache_get], but the content must have the SHA-256
+    /// `pinned` (hexadecimal) if given
+    fn cache_get_verified(
+        &self,
+        url: &str,
+        pinned: Option<&str>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.cache_get_with_policy(url, self.cache_policy(), pinned)
+    }
+
+    /// Same as [Self::cache_get_verified], with an explicit [CachePolicy]
+    fn cache_get_with_policy(
+        &self,
+        url: &str,
+        policy: CachePolicy,
+        pinned: Option<&str>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
         let path = self.cache_path(url)?;
-        dbg!(url, &path, !self.is_valid(&path)?);
-        if !self.is_valid(&path)? {
-            let mut result = self.http_get(url)?;
-            let mut file = File::create(&path)?;
-            io::copy(&mut result, &mut file)?;
+        let refresh = match policy {
+            CachePolicy::OfflineOnly if !path.is_file() => {
+                return Err(format!("{url} isn't cached (offline only)").into());
+            }
+            CachePolicy::OfflineOnly => false,
+            CachePolicy::ForceRefresh => true,
+            CachePolicy::Default | CachePolicy::StaleIfError => !self.is_valid(&path)?,
+        };
+        let _lock = refresh.then(|| CacheLock::acquire(&path)).transpose()?;
+        // an other process can have downloaded it during the wait
+        if refresh && (policy == CachePolicy::ForceRefresh || !self.is_valid(&path)?) {
+            let result = download(self, url, &path, policy != CachePolicy::ForceRefresh);
+            match result {
+                Err(e) if policy == CachePolicy::StaleIfError && path.is_file() => {
+                    self.progress().on_event(&Event::Stale {
+                        url,
+                        error: e.as_ref(),
+                    });
+                }
+                result => result?,
+            }
+        } else {
+            self.progress()
+                .on_event(&Event::CacheHit { url, path: &path });
+        }
+
+        let actual = file_sha256(&path)?;
+        let mut info = CacheInfo::load(&path).unwrap_or_default();
+        let expected = match &info.sha256 {
+            Some(recorded) => recorded.clone(),
+            None => {
+                // cached before digests are recorded
+                info.sha256 = Some(actual.clone());
+                info.url.get_or_insert_with(|| url.into());
+                info.save(&path)?;
+                actual.clone()
+            }
+        };
+        for expected in [Some(expected.as_str()), pinned].into_iter().flatten() {
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(IntegrityError {
+                    url: url.into(),
+                    path,
+                    expected: expected.to_lowercase(),
+                    actual,
+                }
+                .into());
+            }
         }
         Ok(path)
     }
 }
 
+/// Download (or revalidate if `conditional`) url on path of cache
+///
+/// A transient failure on read of content download again the whole content
+/// if the downloader has a [RetryConfig].
+fn download<D>(
+    downloader: &D,
+    url: &str,
+    path: &Path,
+    conditional: bool,
+) -> Result<(), Box<dyn error::Error>>
+where
+    D: Downloader + ?Sized,
+{
+    let attempt = || download_once(downloader, url, path, conditional);
+    match downloader.retry_config() {
+        Some(config) => config.retry(attempt, |e| {
+            e.downcast_ref::<TransferError>()
+                .is_some_and(|e| downloader.is_retryable_io(&e.error))
+        }),
+        None => attempt(),
+    }
+}
+
+fn download_once<D>(
+    downloader: &D,
+    url: &str,
+    path: &Path,
+    conditional: bool,
+) -> Result<(), Box<dyn error::Error>>
+where
+    D: Downloader + ?Sized,
+{
+    let mut info = if conditional && path.is_file() {
+        CacheInfo::load(path).unwrap_or_default()
+    } else {
+        CacheInfo::default()
+    };
+    let progress = downloader.progress();
+    progress.on_event(&Event::CacheMiss { url });
+    match downloader.http_get_conditional(url, &info.validators)? {
+        Conditional::NotModified => {
+            progress.on_event(&Event::NotModified { url });
+            info.revalidated = Some(SystemTime::now());
+        }
+        Conditional::Modified(result, validators) => {
+            let headers = downloader.headers(&result);
+            let total = downloader.content_length(&result);
+            progress.on_event(&Event::Started { url, total });
+            let mut result = HashReader {
+                inner: ProgressReader {
+                    inner: result,
+                    progress,
+                    url,
+                    received: 0,
+                    total,
+                },
+                hasher: Sha256::new(),
+                failed: false,
+            };
+            let written = write_temporary(path, |file| io::copy(&mut result, file).map(|_| ()));
+            let temporary = match written {
+                Err(e) if result.failed => {
+                    return Err(match e.downcast::<io::Error>() {
+                        Ok(error) => Box::new(TransferError {
+                            url: url.into(),
+                            error: *error,
+                        }),
+                        Err(e) => e,
+                    });
+                }
+                written => written?,
+            };
+            // the information of previous content is kept until the new
+            // content is complete
+            if let Err(e) = remove_if_exists(&CacheInfo::path(path)) {
+                let _ = fs::remove_file(&temporary);
+                return Err(e.into());
+            }
+            rename_temporary(&temporary, path)?;
+            progress.on_event(&Event::Finished {
+                url,
+                received: result.inner.received,
+            });
+            info = CacheInfo {
+                url: Some(url.into()),
+                fetched: Some(SystemTime::now()),
+                headers,
+                asset: info.asset,
+                validators,
+                revalidated: None,
+                sha256: Some(hex(&result.hasher.finalize())),
+            };
+        }
+    }
+    info.save(path)?;
+    Ok(())
+}
+
+/// A [Downloader] with an other [CachePolicy], see [Downloader::with_policy]
+pub struct WithPolicy<D> {
+    downloader: D,
+    policy: CachePolicy,
+}
+impl<D: Downloader> Downloader for WithPolicy<D> {
+    type DownloadError = D::DownloadError;
+    type Read = D::Read;
+
+    fn default_validity(&self) -> Duration {
+        self.downloader.default_validity()
+    }
+
+    fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.downloader.cache_path(url)
+    }
+
+    fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+        self.downloader.http_get(url)
+    }
+
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        self.downloader.http_get_conditional(url, validators)
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        self.downloader.content_length(read)
+    }
+
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        self.downloader.headers(read)
+    }
+
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        self.downloader.is_retryable(error)
+    }
+
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        self.downloader.is_retryable_io(error)
+    }
+
+    fn retry_config(&self) -> Option<&RetryConfig> {
+        self.downloader.retry_config()
+    }
+
+    fn is_valid<P: AsRef<Path>>(&self, path: P) -> Result<bool, Box<dyn error::Error>> {
+        self.downloader.is_valid(path)
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    fn progress(&self) -> &dyn Progress {
+        self.downloader.progress()
+    }
+}
+
 impl Downloader for &reqwest::blocking::Client {
     type DownloadError = reqwest::Error;
     type Read = reqwest::blocking::Response;
@@ -51,15 +678,190 @@ impl Downloader for &reqwest::blocking::Client {
     }
 
     fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let path = dirs::cache_dir()
-            .ok_or("Can't determine cache directory")?
-            .join("rust-swissdata");
-        fs::create_dir_all(&path)?;
-        Ok(path.join(urlencoding::encode(url).into_owned()))
+        Ok(Cache::user()?.prepare(url)?)
     }
 
     fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
-        dbg!(url);
         self.get(url).send()?.error_for_status()
     }
+
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        let mut request = self.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request.send()?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        let response = response.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        Ok(Conditional::Modified(response, validators))
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        read.content_length()
+    }
+
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        read.headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.into())))
+            .collect()
+    }
+
+    /// Timeouts, connection failures, server errors (5xx) and too many
+    /// requests (429) are transient
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        error.is_timeout()
+            || error.is_connect()
+            || error.status().is_some_and(|status| {
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+            })
+    }
+
+    /// Also timeouts and failures of body reported by `reqwest`
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        retry::is_transient(error)
+            || error
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<reqwest::Error>())
+                .is_some_and(|e| e.is_timeout() || e.is_body() || e.is_connect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::UNIX_EPOCH;
+
+    use super::mock::Mock;
+    use super::*;
+    use crate::tools::internal::testing::{set_modified, temp_dir, URL};
+
+    #[test]
+    fn cache_info_times() {
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let info = CacheInfo {
+            fetched: Some(time),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["fetched"], "2023-11-14T22:13:20.123456789Z");
+        assert!(json["revalidated"].is_null());
+        let info: CacheInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(info.fetched, Some(time));
+
+        // previous format
+        let json =
+            r#"{"fetched": {"secs_since_epoch": 1700000000, "nanos_since_epoch": 123456789}}"#;
+        let info: CacheInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.fetched, Some(time));
+        assert!(serde_json::from_str::<CacheInfo>(r#"{"fetched": "yesterday"}"#).is_err());
+    }
+
+    #[test]
+    fn cache_policies() {
+        let mock = Mock::new().with_content(URL, "content");
+        let offline = (&mock).with_policy(CachePolicy::OfflineOnly);
+        assert!(offline.cache_get(URL).is_err());
+        assert!(mock.requests().is_empty());
+
+        let path = (&mock).cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "content");
+        (&mock).cache_get(URL).unwrap();
+        assert_eq!(mock.requests().len(), 1);
+
+        assert_eq!(offline.cache_get(URL).unwrap(), path);
+        assert_eq!(mock.requests().len(), 1);
+
+        let refresh = (&mock).with_policy(CachePolicy::ForceRefresh);
+        refresh.cache_get(URL).unwrap();
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn stale_if_error() {
+        let cache = temp_dir();
+        let online = Mock::new()
+            .with_content(URL, "content")
+            .with_cache_dir(cache.path());
+        let path = (&online).cache_get(URL).unwrap();
+        // expire cache
+        set_modified(&path, UNIX_EPOCH);
+
+        let offline = Mock::new().with_cache_dir(cache.path());
+        assert!((&offline).cache_get(URL).is_err());
+        let stale = (&offline).with_policy(CachePolicy::StaleIfError);
+        assert_eq!(stale.cache_get(URL).unwrap(), path);
+        assert_eq!(offline.requests().len(), 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "content");
+        // information is kept after a failed download
+        let info = CacheInfo::load(&path).unwrap();
+        assert_eq!(info.url.as_deref(), Some(URL));
+        assert!(info.sha256.is_some());
+    }
+
+    #[test]
+    fn integrity() {
+        let mock = Mock::new().with_content(URL, "content");
+        let path = (&mock).cache_get(URL).unwrap();
+        let sha256 = file_sha256(&path).unwrap();
+        assert!((&mock).cache_get_verified(URL, Some(&sha256)).is_ok());
+        assert!((&mock).cache_get_verified(URL, Some("00")).is_err());
+
+        fs::write(&path, "altered").unwrap();
+        let error = (&mock).cache_get(URL).unwrap_err();
+        assert!(error.is::<IntegrityError>());
+    }
+
+    #[test]
+    fn revalidation() {
+        let mock = Mock::new()
+            .with_content(URL, "content")
+            .with_etag(URL, "\"v1\"");
+        let path = (&mock).cache_get(URL).unwrap();
+        let info = CacheInfo::load(&path).unwrap();
+        assert_eq!(info.validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(info.revalidated, None);
+
+        set_modified(&path, UNIX_EPOCH);
+        assert!(!(&mock).is_valid(&path).unwrap());
+        let events = RefCell::new(Vec::new());
+        let observed = (&mock).with_progress(|event: &Event| {
+            events.borrow_mut().push(format!("{event:?}"));
+        });
+        assert_eq!(observed.cache_get(URL).unwrap(), path);
+        // If-None-Match is sent, the content isn't downloaded again
+        assert_eq!(mock.validators()[1], info.validators);
+        assert!(events.borrow().iter().any(|e| e.starts_with("NotModified")));
+        assert!(!events.borrow().iter().any(|e| e.starts_with("Started")));
+
+        let revalidated = CacheInfo::load(&path).unwrap();
+        assert!(revalidated.revalidated.is_some());
+        assert_eq!(revalidated.sha256, info.sha256);
+        assert!((&mock).is_valid(&path).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "content");
+
+        // the cache is used until the next expiration
+        (&mock).cache_get(URL).unwrap();
+        assert_eq!(mock.requests().len(), 2);
+    }
 }
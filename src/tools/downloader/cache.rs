@@ -0,0 +1,551 @@
+//! Management of cache directory: list, prune and clear cached files
+//!
+//! [Cache] use the layout of the cache of `reqwest` downloader (see
+//! [Cache::user]): a directory by host, with a file by url named from the
+//! path of url and a hash of url, like
+//! `dam-api.bfs.admin.ch/hub_api_dam_assets_123_master-0123456789abcdef`.
+//! Each cached file has its information in a sidecar (see [CacheInfo]).
+//!
+//! Files of the previous layout (a file by url, named with the encoded url)
+//! are moved on first use of the cache by a process (see [Cache::migrate]),
+//! and again by [Cache::clear] and
+//! prunes move them first, and remove temporary files left by interrupted
+//! downloads.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use swissdata::fso::communes;
+//! use swissdata::tools::downloader::cache::Cache;
+//!
+//! let cache = Cache::user()?;
+//! for entry in cache.entries()? {
+//!     println!("{} {} bytes {:?}", entry.url, entry.size, entry.age());
+//! }
+//! // remove files older than 30 days, then keep at most 1 GB
+//! cache.prune_older_than(Duration::from_secs(30 * 24 * 60 * 60))?;
+//! cache.prune_to_size(1_000_000_000)?;
+//! cache.clear_datastore(&communes::datastore())?;
+//! println!("{} bytes used", cache.disk_usage()?);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeSet, HashSet};
+use std::error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use super::{hex, remove_if_exists, with_suffix, CacheInfo, CacheLock};
+use crate::tools::dataset::Datastore;
+
+/// Maximum length of the part of file name from the path of url
+const NAME_LEN: usize = 64;
+/// Length of the hash of url at end of file name
+const HASH_LEN: usize = 16;
+/// Age of a temporary file after which its download is considered
+/// interrupted, even if its process is running
+const TEMPORARY_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Cache directories migrated by this process (see [Cache::prepare])
+static MIGRATED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Replace characters unsafe in file names by `_`
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Check if a file name is the name of a cached file (not a sidecar)
+fn is_cached_file(name: &str) -> bool {
+    name.rsplit_once('-').is_some_and(|(_, hash)| {
+        hash.len() == HASH_LEN && hash.bytes().all(|b| b.is_ascii_hexdigit())
+    })
+}
+
+/// Names of files owning `name` if it's an information, lock or temporary
+/// file (`<file>.json`, `<file>.lock`, `<file>.<pid>.tmp` or
+/// `<file>.<pid>.<id>.tmp`)
+fn owners(name: &str) -> Vec<&str> {
+    let mut owners: Vec<_> = [".json", ".lock"]
+        .iter()
+        .filter_map(|suffix| name.strip_suffix(suffix))
+        .collect();
+    let mut owner = name.strip_suffix(".tmp");
+    while let Some((rest, id)) = owner.and_then(|owner| owner.rsplit_once('.')) {
+        if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        owners.push(rest);
+        owner = Some(rest);
+    }
+    owners
+}
+
+/// Identifier of process writing `name` if it's a temporary file
+/// (`<file>.<pid>.tmp` or `<file>.<pid>.<id>.tmp`)
+fn temporary_pid(name: &str) -> Option<u32> {
+    let mut ids = name.strip_suffix(".tmp")?.rsplitn(3, '.');
+    let last = ids.next()?.parse().ok()?;
+    // the previous form has no id
+    Some(ids.next().and_then(|id| id.parse().ok()).unwrap_or(last))
+}
+
+/// Check if a process is running (`true` if it can't be checked)
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    !cfg!(target_os = "linux") || !proc.join("self").exists() || proc.join(pid.to_string()).exists()
+}
+
+/// Entry of cache
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// Url of content (empty if unknown)
+    pub url: String,
+    /// Path of cached file
+    pub path: PathBuf,
+    /// Size of cached file (bytes)
+    pub size: u64,
+    /// Time of download
+    pub modified: SystemTime,
+    /// Information recorded with file (default if missing)
+    pub info: CacheInfo,
+}
+impl CacheEntry {
+    /// Time since download
+    pub fn age(&self) -> Duration {
+        self.modified.elapsed().unwrap_or_default()
+    }
+}
+
+/// Directory of cache
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+impl Cache {
+    /// Cache in a directory
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache of user (`rust-swissdata` in cache directory of user), used by
+    /// `reqwest` downloader
+    pub fn user() -> Result<Self, Box<dyn error::Error>> {
+        let dir = dirs::cache_dir().ok_or("Can't determine cache directory")?;
+        Ok(Self::new(dir.join("rust-swissdata")))
+    }
+
+    /// Directory of cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of cached file for url
+    pub fn path(&self, url: &str) -> PathBuf {
+        let (host, resource) = match reqwest::Url::parse(url) {
+            Ok(parsed) => {
+                let host = match (parsed.host_str(), parsed.port()) {
+                    (Some(host), Some(port)) => format!("{host}_{port}"),
+                    (Some(host), None) => host.into(),
+                    (None, _) => "_".into(),
+                };
+                let query = parsed.query().map(|query| format!("?{query}"));
+                (host, parsed.path().to_string() + &query.unwrap_or_default())
+            }
+            Err(_) => ("_".into(), url.into()),
+        };
+        let mut name = sanitize(resource.trim_start_matches('/'));
+        // only ASCII characters after sanitize
+        name.truncate(NAME_LEN);
+        let hash = hex(&Sha256::digest(url));
+        self.dir
+            .join(sanitize(&host))
+            .join(format!("{name}-{}", &hash[..HASH_LEN]))
+    }
+
+    /// Path of cached file for url in the previous layout
+    fn legacy_path(&self, url: &str) -> PathBuf {
+        self.dir.join(urlencoding::encode(url).into_owned())
+    }
+
+    /// Path of cached file for url, ready for download: the directory is
+    /// created and a file of the previous layout is moved (all files on the
+    /// first call for this cache in the process)
+    pub fn prepare(&self, url: &str) -> io::Result<PathBuf> {
+        // marked before migration, which prepare each file
+        let first = MIGRATED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.dir.clone());
+        if first {
+            if let Err(e) = self.migrate() {
+                MIGRATED
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&self.dir);
+                return Err(e);
+            }
+        }
+        let path = self.path(url);
+        fs::create_dir_all(path.parent().unwrap_or(&self.dir))?;
+        let legacy = self.legacy_path(url);
+        if legacy.is_file() {
+            move_file(url, &legacy, &path)?;
+        }
+        Ok(path)
+    }
+
+    /// Move all files of the previous layout, done by the first
+    /// [Self::prepare] of the process
+    pub fn migrate(&self) -> io::Result<()> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            read_dir => read_dir?,
+        };
+        // names are listed before moves, so files owned by a moved file are
+        // still recognised
+        let mut names = HashSet::new();
+        for file in read_dir {
+            let file = file?;
+            if file.file_type()?.is_file() {
+                names.insert(file.file_name().to_string_lossy().into_owned());
+            }
+        }
+        for name in &names {
+            // information files are moved with the file, locks and temporary
+            // files are never cached files
+            let auxiliary = name.ends_with(".lock")
+                || temporary_pid(name).is_some()
+                || owners(name).iter().any(|owner| names.contains(*owner));
+            let Ok(url) = urlencoding::decode(name) else {
+                continue;
+            };
+            if url.contains("://") && !auxiliary {
+                self.prepare(&url)?;
+            }
+        }
+        // locks of moved files (or of files removed by previous versions)
+        for name in names.iter().filter(|name| name.ends_with(".lock")) {
+            let owner = self.dir.join(&name[..name.len() - ".lock".len()]);
+            if !owner.exists() && urlencoding::decode(name).is_ok_and(|url| url.contains("://")) {
+                remove_if_exists(&self.dir.join(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove temporary files of interrupted downloads: of a process which
+    /// isn't running, or older than a day
+    fn remove_temporaries(&self) -> io::Result<()> {
+        for file in self.files()? {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            let Some(pid) = temporary_pid(&name) else {
+                continue;
+            };
+            let age = fs::metadata(&file)?
+                .modified()?
+                .elapsed()
+                .unwrap_or_default();
+            if !is_running(pid) || age > TEMPORARY_MAX_AGE {
+                remove_if_exists(&file)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move files of the previous layout and remove temporary files, before
+    /// a removal of entries
+    fn tidy(&self) -> io::Result<()> {
+        self.migrate()?;
+        self.remove_temporaries()
+    }
+
+    /// List cached files, files of the previous layout aren't listed (see
+    /// [Self::migrate])
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for file in self.files()? {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if file.parent() == Some(&self.dir) || !is_cached_file(&name) {
+                continue;
+            }
+            let metadata = fs::metadata(&file)?;
+            let info = CacheInfo::load(&file).unwrap_or_default();
+            entries.push(CacheEntry {
+                url: info.url.clone().unwrap_or_default(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+                info,
+                path: file,
+            });
+        }
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        Ok(entries)
+    }
+
+    /// Size of all files in cache directory (bytes), with information and
+    /// temporary files
+    pub fn disk_usage(&self) -> io::Result<u64> {
+        self.files()?
+            .iter()
+            .map(|file| Ok(fs::metadata(file)?.len()))
+            .sum()
+    }
+
+    /// Remove cached file of url, return `false` if url isn't cached
+    pub fn remove(&self, url: &str) -> io::Result<bool> {
+        let path = self.path(url);
+        if !path.is_file() {
+            return Ok(false);
+        }
+        remove_file(&path)?;
+        Ok(true)
+    }
+
+    /// Remove an entry
+    pub fn remove_entry(&self, entry: &CacheEntry) -> io::Result<()> {
+        remove_file(&entry.path)
+    }
+
+    /// Remove cached files of a datastore (see [Datastore::urls])
+    pub fn clear_datastore<S, DS: Datastore<S>>(&self, datastore: &DS) -> io::Result<()> {
+        for url in datastore.urls() {
+            self.remove(&url)?;
+        }
+        Ok(())
+    }
+
+    /// Remove all cached files (with files of the previous layout and
+    /// temporary files of interrupted downloads)
+    pub fn clear(&self) -> io::Result<()> {
+        self.tidy()?;
+        for entry in self.entries()? {
+            self.remove_entry(&entry)?;
+        }
+        Ok(())
+    }
+
+    /// Remove files downloaded for more than `max_age`, return removed
+    /// entries
+    pub fn prune_older_than(&self, max_age: Duration) -> io::Result<Vec<CacheEntry>> {
+        self.tidy()?;
+        let mut removed = Vec::new();
+        for entry in self.entries()? {
+            if entry.age() > max_age {
+                self.remove_entry(&entry)?;
+                removed.push(entry);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Remove oldest files until the size of cached files is at most
+    /// `max_size` (bytes), return removed entries
+    pub fn prune_to_size(&self, max_size: u64) -> io::Result<Vec<CacheEntry>> {
+        self.tidy()?;
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.modified);
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut removed = Vec::new();
+        for entry in entries {
+            if size <= max_size {
+                break;
+            }
+            self.remove_entry(&entry)?;
+            size -= entry.size;
+            removed.push(entry);
+        }
+        Ok(removed)
+    }
+
+    /// All files in cache directory and directories of hosts
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let read_dir = match fs::read_dir(&dir) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                read_dir => read_dir?,
+            };
+            for file in read_dir {
+                let file = file?;
+                let file_type = file.file_type()?;
+                if file_type.is_dir() {
+                    dirs.push(file.path());
+                } else if file_type.is_file() {
+                    files.push(file.path());
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Move a cached file of url and its information under the lock of file
+fn move_file(url: &str, from: &Path, to: &Path) -> io::Result<()> {
+    let lock = CacheLock::acquire(from)?;
+    if from.is_file() {
+        if to.exists() {
+            fs::remove_file(from)?;
+        } else {
+            let mut info = CacheInfo::load(from).unwrap_or_default();
+            info.url = Some(url.into());
+            fs::rename(from, to)?;
+            info.save(to).map_err(|e| io::Error::other(e.to_string()))?;
+        }
+        remove_if_exists(&CacheInfo::path(from))?;
+    }
+    drop(lock);
+    // the previous layout is no longer used once the file is moved
+    remove_if_exists(&with_suffix(from, ".lock"))
+}
+
+/// Remove a cached file and its information under the lock of file
+fn remove_file(path: &Path) -> io::Result<()> {
+    let lock = CacheLock::acquire(path)?;
+    remove_if_exists(&CacheInfo::path(path))?;
+    remove_if_exists(path)?;
+    // the lock file is kept: a process waiting on it would lock a removed
+    // file, while an other process lock a new one
+    drop(lock);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::super::mock::Mock;
+    use super::super::Downloader;
+    use super::*;
+    use crate::tools::internal::testing::{set_modified, temp_dir, URL};
+
+    const OTHER: &str = "https://example.org/other.txt";
+
+    /// Files in cache directory, sorted
+    fn files(cache: &Cache) -> Vec<PathBuf> {
+        let mut files = cache.files().unwrap();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn temporary_names() {
+        assert_eq!(
+            temporary_pid("data.txt-0123456789abcdef.12.3.tmp"),
+            Some(12)
+        );
+        assert_eq!(
+            temporary_pid("data.txt-0123456789abcdef.json.12.3.tmp"),
+            Some(12)
+        );
+        assert_eq!(
+            temporary_pid("https%3A%2F%2Fexample.org%2Fdata.txt.12.tmp"),
+            Some(12)
+        );
+        assert_eq!(temporary_pid("https%3A%2F%2Fexample.org%2Fdata.tmp"), None);
+        assert_eq!(temporary_pid("data.txt-0123456789abcdef.lock"), None);
+    }
+
+    #[test]
+    fn prune() {
+        let dir = temp_dir();
+        let mock = Mock::new()
+            .with_content(URL, "content")
+            .with_content(OTHER, "other content")
+            .with_cache_dir(dir.path());
+        let path = (&mock).cache_get(URL).unwrap();
+        let other = (&mock).cache_get(OTHER).unwrap();
+        set_modified(&path, UNIX_EPOCH);
+        let cache = Cache::new(dir.path());
+        assert_eq!(cache.entries().unwrap().len(), 2);
+
+        let removed = cache.prune_older_than(Duration::from_secs(60)).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].url, URL);
+        assert!(!path.exists());
+        assert!(!CacheInfo::path(&path).exists());
+        assert!(other.exists());
+
+        (&mock).cache_get(URL).unwrap();
+        set_modified(&other, UNIX_EPOCH);
+        assert_eq!(cache.prune_to_size(20).unwrap().len(), 0);
+        let removed = cache.prune_to_size(19).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].url, OTHER);
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, URL);
+    }
+
+    #[test]
+    fn migrate_on_first_use() {
+        let dir = temp_dir();
+        let cache = Cache::new(dir.path());
+        let legacy = cache.legacy_path(OTHER);
+        fs::write(&legacy, "other content").unwrap();
+        fs::write(with_suffix(&legacy, ".lock"), "").unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+
+        let mock = Mock::new()
+            .with_content(URL, "content")
+            .with_cache_dir(dir.path());
+        (&mock).cache_get(URL).unwrap();
+        let urls: Vec<_> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.url)
+            .collect();
+        assert_eq!(urls, [URL, OTHER]);
+        assert!(!legacy.exists());
+        assert!(!with_suffix(&legacy, ".lock").exists());
+    }
+
+    #[test]
+    fn clear() {
+        let dir = temp_dir();
+        let mock = Mock::new()
+            .with_content(URL, "content")
+            .with_cache_dir(dir.path());
+        let path = (&mock).cache_get(URL).unwrap();
+        let cache = Cache::new(dir.path());
+        // file of the previous layout, with its information and lock
+        let legacy = cache.legacy_path(OTHER);
+        fs::write(&legacy, "other content").unwrap();
+        fs::write(CacheInfo::path(&legacy), "{}").unwrap();
+        fs::write(with_suffix(&legacy, ".lock"), "").unwrap();
+        // temporary files of a stopped process, of an old download and of a
+        // running download
+        let stopped = with_suffix(&path, &format!(".{}.0.tmp", u32::MAX));
+        let old = with_suffix(&path, &format!(".{}.0.tmp", std::process::id()));
+        let running = with_suffix(&path, &format!(".{}.1.tmp", std::process::id()));
+        for temporary in [&stopped, &old, &running] {
+            fs::write(temporary, "partial").unwrap();
+        }
+        set_modified(&old, UNIX_EPOCH);
+
+        cache.clear().unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+        // locks are kept
+        let mut expected = vec![
+            with_suffix(&path, ".lock"),
+            with_suffix(cache.path(OTHER), ".lock"),
+            running,
+        ];
+        if is_running(u32::MAX) {
+            expected.push(stopped);
+        }
+        expected.sort();
+        assert_eq!(files(&cache), expected);
+    }
+}
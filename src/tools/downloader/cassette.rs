@@ -0,0 +1,306 @@
+//! Record responses of a [Downloader] in a cassette directory and replay
+//! them without network
+//!
+//! A cassette use the layout of [Cache] (see [Cache::entries]), the
+//! information of each response (url, headers, SHA-256) is in its sidecar
+//! (see [CacheInfo]), a replayed content must match its SHA-256. In both modes,
+//! [Downloader::cache_get] always get the content through the cassette
+//! ([CachePolicy::ForceRefresh]) and write it in the cache of wrapped
+//! downloader.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::tools::downloader::cassette::CassetteMode;
+//! use swissdata::tools::downloader::mock::Mock;
+//! use swissdata::tools::Downloader;
+//!
+//! // once, with network
+//! let client = reqwest::blocking::Client::new();
+//! communes::datastore().load((&client).with_cassette("tests/cassettes", CassetteMode::Record))?;
+//!
+//! // in tests, without network (the mock only provide a temporary cache)
+//! let mock = Mock::new();
+//! let ds = communes::datastore().load((&mock).with_cassette("tests/cassettes", CassetteMode::Replay))?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use super::cache::Cache;
+use super::progress::Progress;
+use super::retry::RetryConfig;
+use super::{
+    file_sha256, hex, remove_if_exists, rename_temporary, write_temporary, CacheInfo, CachePolicy,
+    Downloader, HashReader, IntegrityError,
+};
+
+/// Mode of [Cassette]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Download with the wrapped downloader and save responses
+    Record,
+    /// Serve saved responses, never use the wrapped downloader
+    Replay,
+}
+
+/// Error of [Cassette]
+#[derive(Debug)]
+pub enum CassetteError<E> {
+    /// Error of wrapped downloader (on record)
+    Download(E),
+    /// Error on read of content (on record)
+    Transfer(io::Error),
+    /// Error on read or write of cassette
+    Io(io::Error),
+    /// No response saved for url (on replay)
+    Unknown(String),
+    /// Saved content doesn't match its SHA-256 (on replay)
+    Integrity(IntegrityError),
+}
+impl<E: fmt::Display> fmt::Display for CassetteError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Download(e) => write!(fmt, "{e}"),
+            Self::Transfer(e) => write!(fmt, "Transfer of content failed: {e}"),
+            Self::Io(e) => write!(fmt, "Cassette error: {e}"),
+            Self::Unknown(url) => write!(fmt, "No response recorded for {url}"),
+            Self::Integrity(e) => write!(fmt, "Cassette error: {e}"),
+        }
+    }
+}
+impl<E: error::Error + 'static> error::Error for CassetteError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Download(e) => Some(e),
+            Self::Transfer(e) | Self::Io(e) => Some(e),
+            Self::Integrity(e) => Some(e),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+impl<E> From<io::Error> for CassetteError<E> {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Saved response, read from the cassette
+pub struct Recording {
+    content: File,
+    len: u64,
+    headers: BTreeMap<String, String>,
+}
+impl Recording {
+    fn open(path: &Path, headers: BTreeMap<String, String>) -> io::Result<Self> {
+        let content = File::open(path)?;
+        Ok(Self {
+            len: content.metadata()?.len(),
+            content,
+            headers,
+        })
+    }
+}
+impl Read for Recording {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.content.read(buf)
+    }
+}
+
+/// Error of a write in cassette as an I/O error
+fn io_error(error: Box<dyn error::Error>) -> io::Error {
+    match error.downcast::<io::Error>() {
+        Ok(error) => *error,
+        Err(error) => io::Error::other(error.to_string()),
+    }
+}
+
+/// A [Downloader] recording or replaying responses, see
+/// [Downloader::with_cassette]
+pub struct Cassette<D> {
+    pub(super) downloader: D,
+    pub(super) cassette: Cache,
+    pub(super) mode: CassetteMode,
+}
+impl<D: Downloader> Cassette<D> {
+    /// Record a response, a transient failure on read of content download
+    /// again the whole content if the wrapped downloader has a [RetryConfig]
+    fn record(&self, url: &str) -> Result<Recording, CassetteError<D::DownloadError>> {
+        let attempt = || self.record_once(url);
+        match self.downloader.retry_config() {
+            Some(config) => config.retry(
+                attempt,
+                |e| matches!(e, CassetteError::Transfer(e) if self.downloader.is_retryable_io(e)),
+            ),
+            None => attempt(),
+        }
+    }
+
+    fn record_once(&self, url: &str) -> Result<Recording, CassetteError<D::DownloadError>> {
+        let response = self
+            .downloader
+            .http_get(url)
+            .map_err(CassetteError::Download)?;
+        let headers = self.downloader.headers(&response);
+        let path = self.cassette.prepare(url)?;
+        let mut content = HashReader {
+            inner: response,
+            hasher: Sha256::new(),
+            failed: false,
+        };
+        let written = write_temporary(&path, |file| io::copy(&mut content, file).map(|_| ()));
+        let temporary = match written {
+            Err(e) if content.failed => return Err(CassetteError::Transfer(io_error(e))),
+            written => written.map_err(io_error)?,
+        };
+        // the information of previous response never describe the new one
+        if let Err(e) = remove_if_exists(&CacheInfo::path(&path)) {
+            let _ = remove_if_exists(&temporary);
+            return Err(e.into());
+        }
+        rename_temporary(&temporary, &path).map_err(io_error)?;
+        CacheInfo {
+            url: Some(url.into()),
+            fetched: Some(SystemTime::now()),
+            headers: headers.clone(),
+            sha256: Some(hex(&content.hasher.finalize())),
+            ..Default::default()
+        }
+        .save(&path)
+        .map_err(io_error)?;
+        Ok(Recording::open(&path, headers)?)
+    }
+
+    fn replay(&self, url: &str) -> Result<Recording, CassetteError<D::DownloadError>> {
+        let path = self.cassette.path(url);
+        if !path.is_file() {
+            return Err(CassetteError::Unknown(url.into()));
+        }
+        let info = CacheInfo::load(&path).unwrap_or_default();
+        if let Some(expected) = info.sha256 {
+            let actual = file_sha256(&path)?;
+            if actual != expected {
+                return Err(CassetteError::Integrity(IntegrityError {
+                    url: url.into(),
+                    path,
+                    expected,
+                    actual,
+                }));
+            }
+        }
+        Ok(Recording::open(&path, info.headers)?)
+    }
+}
+impl<D: Downloader> Downloader for Cassette<D> {
+    type DownloadError = CassetteError<D::DownloadError>;
+    type Read = Recording;
+
+    fn default_validity(&self) -> Duration {
+        self.downloader.default_validity()
+    }
+
+    fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn error::Error>> {
+        self.downloader.cache_path(url)
+    }
+
+    fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+        match self.mode {
+            CassetteMode::Record => self.record(url),
+            CassetteMode::Replay => self.replay(url),
+        }
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        Some(read.len)
+    }
+
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        read.headers.clone()
+    }
+
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        match error {
+            CassetteError::Download(e) => self.downloader.is_retryable(e),
+            CassetteError::Transfer(e) => self.downloader.is_retryable_io(e),
+            CassetteError::Io(_) | CassetteError::Unknown(_) | CassetteError::Integrity(_) => false,
+        }
+    }
+
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        self.downloader.is_retryable_io(error)
+    }
+
+    fn retry_config(&self) -> Option<&RetryConfig> {
+        self.downloader.retry_config()
+    }
+
+    fn is_valid<P: AsRef<Path>>(&self, path: P) -> Result<bool, Box<dyn error::Error>> {
+        self.downloader.is_valid(path)
+    }
+
+    /// Always [CachePolicy::ForceRefresh], so all contents are recorded or
+    /// replayed
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::ForceRefresh
+    }
+
+    fn progress(&self) -> &dyn Progress {
+        self.downloader.progress()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::super::mock::Mock;
+    use super::*;
+    use crate::tools::internal::testing::{temp_dir, URL};
+
+    #[test]
+    fn record_and_replay() {
+        let cassette = temp_dir();
+        let online = Mock::new().with_content(URL, "content");
+        let recorder = (&online).with_cassette(cassette.path(), CassetteMode::Record);
+        let path = recorder.cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+        assert_eq!(online.requests(), [URL]);
+
+        let offline = Mock::new();
+        let player = (&offline).with_cassette(cassette.path(), CassetteMode::Replay);
+        let path = player.cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+        assert!(offline.requests().is_empty());
+
+        assert!(player.cache_get("https://example.org/other.txt").is_err());
+    }
+
+    #[test]
+    fn corrupted_cassette() {
+        let cassette = temp_dir();
+        let online = Mock::new().with_content(URL, "content");
+        let recorder = (&online).with_cassette(cassette.path(), CassetteMode::Record);
+        recorder.cache_get(URL).unwrap();
+        let recorded = Cache::new(cassette.path()).path(URL);
+        fs::write(&recorded, "altered").unwrap();
+
+        let offline = Mock::new();
+        let player = (&offline).with_cassette(cassette.path(), CassetteMode::Replay);
+        match player.http_get(URL) {
+            Err(CassetteError::Integrity(e)) => {
+                assert_eq!(e.expected, hex(&Sha256::digest("content")));
+                assert_eq!(e.path, recorded);
+            }
+            _ => panic!("altered content replayed"),
+        }
+        assert!(player.cache_get(URL).is_err());
+    }
+}
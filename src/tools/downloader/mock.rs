@@ -0,0 +1,272 @@
+//! [Downloader] serving content from a fixture directory or from memory,
+//! without network, for tests
+//!
+//! The content of an url is searched in memory (see [Mock::with_content]),
+//! then in the fixture directory at `<host>/<path of url>` (the query is
+//! ignored), like `dam-api.bfs.admin.ch/hub/api/dam/assets/123/master`.
+//! Downloads use a new temporary cache, removed on drop, so each mock
+//! start with an empty cache. An url with an `ETag` (see [Mock::with_etag])
+//! answer conditional requests like a server.
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::tools::downloader::mock::Mock;
+//!
+//! let mock = Mock::from_dir("tests/fixtures");
+//! let ds = communes::datastore().load(&mock)?;
+//! assert_eq!(mock.requests(), communes::datastore().urls());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::error;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::cache::Cache;
+use super::{Conditional, Downloader, Validators};
+
+/// Counter of temporary caches of this process
+static CACHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Mock of downloader, see [module](self)
+#[derive(Debug)]
+pub struct Mock {
+    contents: HashMap<String, Vec<u8>>,
+    etags: HashMap<String, String>,
+    dir: Option<PathBuf>,
+    cache: Cache,
+    temporary: bool,
+    requests: Mutex<Vec<(String, Validators)>>,
+}
+impl Default for Mock {
+    fn default() -> Self {
+        let id = CACHES.fetch_add(1, Ordering::Relaxed);
+        let dir =
+            std::env::temp_dir().join(format!("rust-swissdata-mock-{}-{id}", std::process::id()));
+        Self {
+            contents: HashMap::new(),
+            etags: HashMap::new(),
+            dir: None,
+            cache: Cache::new(dir),
+            temporary: true,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+}
+impl Mock {
+    /// Mock without content, add content with [Self::with_content]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mock serving files of a fixture directory
+    pub fn from_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        let mut mock = Self::default();
+        mock.dir = Some(dir.into());
+        mock
+    }
+
+    /// Serve `content` for `url`
+    pub fn with_content<U, C>(mut self, url: U, content: C) -> Self
+    where
+        U: Into<String>,
+        C: Into<Vec<u8>>,
+    {
+        self.contents.insert(url.into(), content.into());
+        self
+    }
+
+    /// Answer requests of `url` with `etag`, a conditional request with the
+    /// same `If-None-Match` get a not modified response
+    pub fn with_etag<U, E>(mut self, url: U, etag: E) -> Self
+    where
+        U: Into<String>,
+        E: Into<String>,
+    {
+        self.etags.insert(url.into(), etag.into());
+        self
+    }
+
+    /// Use a cache directory (kept on drop) instead of a temporary cache
+    pub fn with_cache_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        if self.temporary {
+            let _ = fs::remove_dir_all(self.cache.dir());
+        }
+        self.cache = Cache::new(dir);
+        self.temporary = false;
+        self
+    }
+
+    /// Urls requested (without cache hits), in order of requests
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    /// Validators sent with each request (empty if the request isn't
+    /// conditional), in order of requests
+    pub fn validators(&self) -> Vec<Validators> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(_, validators)| validators.clone())
+            .collect()
+    }
+
+    /// Forget requested urls
+    pub fn clear_requests(&self) {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Path of fixture for url in fixture directory
+    fn fixture(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let parsed = reqwest::Url::parse(url).ok()?;
+        let mut path = dir.join(parsed.host_str()?);
+        path.extend(
+            parsed
+                .path_segments()?
+                .filter(|segment| !segment.is_empty()),
+        );
+        Some(path)
+    }
+
+    /// Record a request
+    fn record(&self, url: &str, validators: &Validators) {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((url.into(), validators.clone()));
+    }
+
+    /// Content of url, from memory or fixture directory
+    fn content(&self, url: &str) -> io::Result<Cursor<Vec<u8>>> {
+        if let Some(content) = self.contents.get(url) {
+            return Ok(Cursor::new(content.clone()));
+        }
+        match self.fixture(url).filter(|path| path.is_file()) {
+            Some(path) => Ok(Cursor::new(fs::read(path)?)),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No content for {url}"),
+            )),
+        }
+    }
+}
+impl Drop for Mock {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(self.cache.dir());
+        }
+    }
+}
+
+impl Downloader for &Mock {
+    type DownloadError = io::Error;
+    type Read = Cursor<Vec<u8>>;
+
+    fn default_validity(&self) -> Duration {
+        Duration::new(60 * 60 * 24, 0)
+    }
+
+    fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn error::Error>> {
+        Ok(self.cache.prepare(url)?)
+    }
+
+    /// Content of url, an error of kind [io::ErrorKind::NotFound] if there is
+    /// no content for url
+    fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+        self.record(url, &Validators::default());
+        self.content(url)
+    }
+
+    /// Not modified if url has the `ETag` of validators (see
+    /// [Mock::with_etag])
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        self.record(url, validators);
+        let etag = self.etags.get(url);
+        if etag.is_some() && validators.etag.as_ref() == etag {
+            return Ok(Conditional::NotModified);
+        }
+        let validators = Validators {
+            etag: etag.cloned(),
+            last_modified: None,
+        };
+        Ok(Conditional::Modified(self.content(url)?, validators))
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        Some(read.get_ref().len() as u64)
+    }
+
+    /// Missing contents aren't transient
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        error.kind() != io::ErrorKind::NotFound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fso::communes;
+    use crate::tools::dataset::Datastore;
+    use crate::tools::internal::testing::{communes_zip, temp_dir};
+
+    #[test]
+    fn load_communes() {
+        let store = communes::datastore();
+        let url = store.urls().remove(0);
+        let mock = Mock::new().with_content(url.clone(), communes_zip());
+
+        let ds = store.load(&mock).unwrap();
+        let municipalities: Vec<_> = ds.municipalities.iter().collect::<Result<_, _>>().unwrap();
+        assert_eq!(municipalities.len(), 1);
+        assert_eq!(municipalities[0].name, "Aeugst am Albis");
+        assert_eq!(ds.cantons.iter().count(), 1);
+        assert_eq!(ds.districts.iter().count(), 1);
+        assert_eq!(mock.requests(), std::slice::from_ref(&url));
+
+        // second load from cache
+        store.load(&mock).unwrap();
+        assert_eq!(mock.requests(), [url]);
+    }
+
+    #[test]
+    fn missing_content() {
+        let mock = Mock::new();
+        assert!(communes::datastore().load(&mock).is_err());
+        assert_eq!(mock.requests(), communes::datastore().urls());
+    }
+
+    #[test]
+    fn fixture_directory() {
+        let fixtures = temp_dir();
+        let file = fixtures.path().join("example.org/data/file.txt");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "content").unwrap();
+
+        let mock = Mock::from_dir(fixtures.path());
+        let path = (&mock)
+            .cache_get("https://example.org/data/file.txt?version=1")
+            .unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+    }
+}
@@ -0,0 +1,207 @@
+//! Progress of downloads and usage of cache
+//!
+//! [Downloader::cache_get] report [Event]s to the [Progress] of downloader
+//! (see [Downloader::with_progress]), any `Fn(&Event)` is a [Progress].
+//!
+//! ```no_run
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::tools::downloader::progress::Event;
+//! use swissdata::tools::Downloader;
+//!
+//! let client = reqwest::blocking::Client::new();
+//! let downloader = (&client).with_progress(|event: &Event| match event {
+//!     Event::Progress {
+//!         received,
+//!         total: Some(total),
+//!         ..
+//!     } => eprint!("\r{}%", received * 100 / total.max(&1)),
+//!     Event::Finished { .. } => eprintln!(),
+//!     _ => {}
+//! });
+//! let ds = communes::datastore().load(downloader)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::error;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::retry::RetryConfig;
+use super::{CachePolicy, Conditional, Downloader, Validators};
+
+/// Event reported by [Downloader::cache_get]
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// Cached file is used without request
+    CacheHit {
+        /// Url of content
+        url: &'a str,
+        /// Path of cached file
+        path: &'a Path,
+    },
+    /// Cache is missing or expired, url is requested
+    CacheMiss {
+        /// Url of content
+        url: &'a str,
+    },
+    /// Server confirm the cached file is up to date
+    NotModified {
+        /// Url of content
+        url: &'a str,
+    },
+    /// Download of content start
+    Started {
+        /// Url of content
+        url: &'a str,
+        /// Size of content (`Content-Length`) if known
+        total: Option<u64>,
+    },
+    /// Part of content is received, reported on each read (an observer
+    /// updating a display should throttle it)
+    Progress {
+        /// Url of content
+        url: &'a str,
+        /// Bytes received since start
+        received: u64,
+        /// Size of content (`Content-Length`) if known
+        total: Option<u64>,
+    },
+    /// Content is completely downloaded and cached
+    Finished {
+        /// Url of content
+        url: &'a str,
+        /// Bytes received
+        received: u64,
+    },
+    /// Download fail and the expired cache is used (see
+    /// [CachePolicy::StaleIfError])
+    Stale {
+        /// Url of content
+        url: &'a str,
+        /// Error of download
+        error: &'a dyn error::Error,
+    },
+}
+
+/// Observer of downloads
+pub trait Progress {
+    /// Called on each event
+    fn on_event(&self, event: &Event);
+}
+impl<F: Fn(&Event)> Progress for F {
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Observer ignoring all events, default of [Downloader::progress]
+impl Progress for () {
+    fn on_event(&self, _event: &Event) {}
+}
+
+/// Observer printing only warnings (use of expired cache) on stderr
+///
+/// ```no_run
+/// use swissdata::tools::downloader::progress::Warnings;
+/// use swissdata::tools::Downloader;
+///
+/// let client = reqwest::blocking::Client::new();
+/// let downloader = (&client).with_progress(Warnings);
+/// ```
+pub struct Warnings;
+impl Progress for Warnings {
+    fn on_event(&self, event: &Event) {
+        if let Event::Stale { url, error } = event {
+            eprintln!("Use expired cache for {url}: {error}");
+        }
+    }
+}
+
+/// Reader reporting [Event::Progress]
+pub(super) struct ProgressReader<'a, R> {
+    pub(super) inner: R,
+    pub(super) progress: &'a dyn Progress,
+    pub(super) url: &'a str,
+    pub(super) received: u64,
+    pub(super) total: Option<u64>,
+}
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if len > 0 {
+            self.received += len as u64;
+            self.progress.on_event(&Event::Progress {
+                url: self.url,
+                received: self.received,
+                total: self.total,
+            });
+        }
+        Ok(len)
+    }
+}
+
+/// A [Downloader] reporting to an other [Progress], see
+/// [Downloader::with_progress]
+pub struct WithProgress<D, P> {
+    pub(super) downloader: D,
+    pub(super) progress: P,
+}
+impl<D: Downloader, P: Progress> Downloader for WithProgress<D, P> {
+    type DownloadError = D::DownloadError;
+    type Read = D::Read;
+
+    fn default_validity(&self) -> Duration {
+        self.downloader.default_validity()
+    }
+
+    fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn error::Error>> {
+        self.downloader.cache_path(url)
+    }
+
+    fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+        self.downloader.http_get(url)
+    }
+
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        self.downloader.http_get_conditional(url, validators)
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        self.downloader.content_length(read)
+    }
+
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        self.downloader.headers(read)
+    }
+
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        self.downloader.is_retryable(error)
+    }
+
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        self.downloader.is_retryable_io(error)
+    }
+
+    fn retry_config(&self) -> Option<&RetryConfig> {
+        self.downloader.retry_config()
+    }
+
+    fn is_valid<Q: AsRef<Path>>(&self, path: Q) -> Result<bool, Box<dyn error::Error>> {
+        self.downloader.is_valid(path)
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.downloader.cache_policy()
+    }
+
+    fn progress(&self) -> &dyn Progress {
+        &self.progress
+    }
+}
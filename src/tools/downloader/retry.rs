@@ -0,0 +1,323 @@
+//! Retry of downloads with exponential backoff, and timeouts of HTTP client
+//!
+//! [Retry] wrap any [Downloader], a failed request is retried if the error
+//! is transient (see [Downloader::is_retryable]). A transient failure on
+//! read of content (see [Downloader::is_retryable_io]) is retried by
+//! [Downloader::cache_get] and by the record of a
+//! [Cassette](super::cassette::Cassette), which download again the whole
+//! content, but not on read of a response returned by [Downloader::http_get].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use swissdata::fso::communes;
+//! use swissdata::tools::dataset::Datastore;
+//! use swissdata::tools::downloader::retry::{RetryConfig, Timeouts};
+//! use swissdata::tools::Downloader;
+//!
+//! let client = Timeouts {
+//!     connect: Duration::from_secs(10),
+//!     read: Duration::from_secs(60),
+//! }
+//! .client()?;
+//! let downloader = (&client).with_retry(RetryConfig {
+//!     retries: 5,
+//!     ..Default::default()
+//! });
+//! let ds = communes::datastore().load(downloader)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::error;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use super::progress::Progress;
+use super::{CachePolicy, Conditional, Downloader, Validators};
+
+/// Configuration of retries
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+    /// Wait before the first retry, doubled on each retry
+    pub backoff: Duration,
+    /// Maximum wait between two attempts
+    pub max_backoff: Duration,
+}
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+impl RetryConfig {
+    /// Wait before retry, half of backoff is random (jitter) to spread
+    /// retries of parallel clients
+    fn wait(&self, backoff: Duration) -> Duration {
+        let random = RandomState::new().build_hasher().finish();
+        let half = backoff / 2;
+        half + half.mul_f64(random as f64 / u64::MAX as f64)
+    }
+
+    pub(super) fn retry<T, E, F, R>(&self, mut attempt: F, retryable: R) -> Result<T, E>
+    where
+        F: FnMut() -> Result<T, E>,
+        R: Fn(&E) -> bool,
+    {
+        let mut backoff = self.backoff.min(self.max_backoff);
+        for _ in 0..self.retries {
+            match attempt() {
+                Err(e) if retryable(&e) => {
+                    thread::sleep(self.wait(backoff));
+                    backoff = (backoff * 2).min(self.max_backoff);
+                }
+                result => return result,
+            }
+        }
+        attempt()
+    }
+}
+
+/// Check if an error on read of content is transient
+pub(super) fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::Interrupted
+    )
+}
+
+/// A [Downloader] retrying transient failures, see [Downloader::with_retry]
+pub struct Retry<D> {
+    pub(super) downloader: D,
+    pub(super) config: RetryConfig,
+}
+impl<D: Downloader> Downloader for Retry<D> {
+    type DownloadError = D::DownloadError;
+    type Read = D::Read;
+
+    fn default_validity(&self) -> Duration {
+        self.downloader.default_validity()
+    }
+
+    fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn error::Error>> {
+        self.downloader.cache_path(url)
+    }
+
+    fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+        self.config.retry(
+            || self.downloader.http_get(url),
+            |e| self.downloader.is_retryable(e),
+        )
+    }
+
+    fn http_get_conditional(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Conditional<Self::Read>, Self::DownloadError> {
+        self.config.retry(
+            || self.downloader.http_get_conditional(url, validators),
+            |e| self.downloader.is_retryable(e),
+        )
+    }
+
+    fn content_length(&self, read: &Self::Read) -> Option<u64> {
+        self.downloader.content_length(read)
+    }
+
+    fn headers(&self, read: &Self::Read) -> BTreeMap<String, String> {
+        self.downloader.headers(read)
+    }
+
+    fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+        self.downloader.is_retryable(error)
+    }
+
+    fn is_retryable_io(&self, error: &io::Error) -> bool {
+        self.downloader.is_retryable_io(error)
+    }
+
+    fn retry_config(&self) -> Option<&RetryConfig> {
+        Some(&self.config)
+    }
+
+    fn is_valid<P: AsRef<Path>>(&self, path: P) -> Result<bool, Box<dyn error::Error>> {
+        self.downloader.is_valid(path)
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.downloader.cache_policy()
+    }
+
+    fn progress(&self) -> &dyn Progress {
+        self.downloader.progress()
+    }
+}
+
+/// Timeouts of HTTP client
+#[derive(Clone, Debug)]
+pub struct Timeouts {
+    /// Timeout of connection
+    pub connect: Duration,
+    /// Timeout of each read (and write) operation
+    pub read: Duration,
+}
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(30),
+            read: Duration::from_secs(30),
+        }
+    }
+}
+impl Timeouts {
+    /// Build a HTTP client with these timeouts
+    pub fn client(&self) -> reqwest::Result<reqwest::blocking::Client> {
+        reqwest::blocking::Client::builder()
+            .connect_timeout(self.connect)
+            .timeout(self.read)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::fs;
+    use std::io::{Cursor, Read};
+
+    use super::super::cassette::CassetteMode;
+    use super::super::mock::Mock;
+    use super::*;
+    use crate::tools::internal::testing::{temp_dir, URL};
+
+    /// Downloader failing the first requests, on request or on read of
+    /// content
+    struct Flaky {
+        mock: Mock,
+        failures: Cell<usize>,
+        on_read: bool,
+        attempts: Cell<usize>,
+    }
+    impl Flaky {
+        fn new(failures: usize, on_read: bool) -> Self {
+            Self {
+                mock: Mock::new().with_content(URL, "content"),
+                failures: Cell::new(failures),
+                on_read,
+                attempts: Cell::new(0),
+            }
+        }
+    }
+
+    /// Content failing on first read if `failure` is set
+    struct FlakyRead {
+        inner: Cursor<Vec<u8>>,
+        failure: bool,
+    }
+    impl Read for FlakyRead {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if std::mem::take(&mut self.failure) {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Downloader for &Flaky {
+        type DownloadError = io::Error;
+        type Read = FlakyRead;
+
+        fn default_validity(&self) -> Duration {
+            (&self.mock).default_validity()
+        }
+
+        fn cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn error::Error>> {
+            (&self.mock).cache_path(url)
+        }
+
+        fn http_get(&self, url: &str) -> Result<Self::Read, Self::DownloadError> {
+            self.attempts.set(self.attempts.get() + 1);
+            let failure = self.failures.get() > 0;
+            self.failures.set(self.failures.get().saturating_sub(1));
+            if failure && !self.on_read {
+                return Err(io::ErrorKind::ConnectionReset.into());
+            }
+            Ok(FlakyRead {
+                inner: (&self.mock).http_get(url)?,
+                failure,
+            })
+        }
+
+        fn is_retryable(&self, error: &Self::DownloadError) -> bool {
+            (&self.mock).is_retryable(error)
+        }
+    }
+
+    fn config(retries: u32) -> RetryConfig {
+        RetryConfig {
+            retries,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn transient_requests() {
+        let flaky = Flaky::new(2, false);
+        let path = (&flaky).with_retry(config(3)).cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+        assert_eq!(flaky.attempts.get(), 3);
+
+        let flaky = Flaky::new(5, false);
+        assert!((&flaky).with_retry(config(2)).cache_get(URL).is_err());
+        assert_eq!(flaky.attempts.get(), 3);
+    }
+
+    #[test]
+    fn permanent_errors() {
+        let mock = Mock::new();
+        assert!((&mock).with_retry(config(3)).cache_get(URL).is_err());
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[test]
+    fn transient_reads() {
+        let flaky = Flaky::new(1, true);
+        assert!((&flaky).cache_get(URL).is_err());
+        assert_eq!(flaky.attempts.get(), 1);
+        let path = (&flaky).cache_path(URL).unwrap();
+        assert!(!path.exists());
+
+        let flaky = Flaky::new(1, true);
+        let path = (&flaky).with_retry(config(3)).cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+        assert_eq!(flaky.attempts.get(), 2);
+    }
+
+    #[test]
+    fn transient_reads_on_record() {
+        let cassette = temp_dir();
+        let flaky = Flaky::new(1, true);
+        let recorder = (&flaky)
+            .with_retry(config(3))
+            .with_cassette(cassette.path(), CassetteMode::Record);
+        let path = recorder.cache_get(URL).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "content");
+        assert_eq!(flaky.attempts.get(), 2);
+    }
+}
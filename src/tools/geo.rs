@@ -0,0 +1,535 @@
+//! Swiss coordinate reference systems
+//!
+//! - [Lv95]: CH1903+ / LV95 (EPSG:2056), used by swiss geographic datasets
+//! - [Lv03]: CH1903 / LV03 (EPSG:21781), previous swiss reference frame
+//! - [Wgs84]: WGS84 (EPSG:4326), latitude and longitude on degrees
+//!
+//! Conversions with [From] use the approximation formulas of swisstopo
+//! (*Approximate formulas for the transformation between Swiss projection
+//! coordinates and WGS84*), accuracy is about 1 meter. LV03 and LV95
+//! differ of a translation and local distortions up to 1.6 meter, [Ntv2Grid]
+//! with the CHENyx06 grid of swisstopo gives the precise transformation.
+//!
+//! ```
+//! use swissdata::tools::geo::{Lv03, Lv95, Wgs84};
+//!
+//! let bern = Wgs84 {
+//!     latitude: 46.951082877,
+//!     longitude: 7.438632495,
+//! };
+//! let lv95 = Lv95::from(bern);
+//! assert!((lv95.east - 2_600_000.).abs() < 1.);
+//! assert!((lv95.north - 1_200_000.).abs() < 1.);
+//! let lv03 = Lv03::from(lv95);
+//! assert_eq!((lv03.east, lv03.north), (lv95.east - 2_000_000., lv95.north - 1_000_000.));
+//! ```
+
+use std::error;
+use std::f64::consts::FRAC_PI_4;
+
+use serde::{Deserialize, Serialize};
+
+/// Coordinate on LV95 (EPSG:2056) in meter
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lv95 {
+    /// East (E), like `2600000` for Bern
+    pub east: f64,
+    /// North (N), like `1200000` for Bern
+    pub north: f64,
+}
+
+/// Coordinate on LV03 (EPSG:21781) in meter
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lv03 {
+    /// East (y), like `600000` for Bern
+    pub east: f64,
+    /// North (x), like `200000` for Bern
+    pub north: f64,
+}
+
+/// Coordinate on WGS84 (EPSG:4326) in degree
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Wgs84 {
+    /// Latitude (north)
+    pub latitude: f64,
+    /// Longitude (east)
+    pub longitude: f64,
+}
+
+impl From<[f64; 2]> for Lv95 {
+    /// From `[east, north]`
+    fn from([east, north]: [f64; 2]) -> Self {
+        Self { east, north }
+    }
+}
+
+impl From<[f64; 2]> for Lv03 {
+    /// From `[east, north]` (`[y, x]`)
+    fn from([east, north]: [f64; 2]) -> Self {
+        Self { east, north }
+    }
+}
+
+impl From<Lv03> for Lv95 {
+    /// Translation only, see [Ntv2Grid] for precise transformation
+    fn from(lv03: Lv03) -> Self {
+        Self {
+            east: lv03.east + 2_000_000.,
+            north: lv03.north + 1_000_000.,
+        }
+    }
+}
+
+impl From<Lv95> for Lv03 {
+    /// Translation only, see [Ntv2Grid] for precise transformation
+    fn from(lv95: Lv95) -> Self {
+        Self {
+            east: lv95.east - 2_000_000.,
+            north: lv95.north - 1_000_000.,
+        }
+    }
+}
+
+impl From<Wgs84> for Lv95 {
+    /// Approximation formulas of swisstopo
+    fn from(wgs84: Wgs84) -> Self {
+        // auxiliary values, difference to Bern on 10000 seconds
+        let phi = (wgs84.latitude * 3600. - 169_028.66) / 10_000.;
+        let lambda = (wgs84.longitude * 3600. - 26_782.5) / 10_000.;
+        Self {
+            east: 2_600_072.37 + 211_455.93 * lambda
+                - 10_938.51 * lambda * phi
+                - 0.36 * lambda * phi.powi(2)
+                - 44.54 * lambda.powi(3),
+            north: 1_200_147.07
+                + 308_807.95 * phi
+                + 3_745.25 * lambda.powi(2)
+                + 76.63 * phi.powi(2)
+                - 194.56 * lambda.powi(2) * phi
+                + 119.79 * phi.powi(3),
+        }
+    }
+}
+
+impl From<Lv95> for Wgs84 {
+    /// Approximation formulas of swisstopo
+    fn from(lv95: Lv95) -> Self {
+        // auxiliary values, difference to Bern on 1000 kilometers
+        let y = (lv95.east - 2_600_000.) / 1_000_000.;
+        let x = (lv95.north - 1_200_000.) / 1_000_000.;
+        let lambda = 2.677_909_4 + 4.728_982 * y + 0.791_484 * y * x + 0.130_6 * y * x.powi(2)
+            - 0.043_6 * y.powi(3);
+        let phi = 16.902_389_2 + 3.238_272 * x
+            - 0.270_978 * y.powi(2)
+            - 0.002_528 * x.powi(2)
+            - 0.044_7 * y.powi(2) * x
+            - 0.014_0 * x.powi(3);
+        // unit 10000" to degree
+        Self {
+            latitude: phi * 100. / 36.,
+            longitude: lambda * 100. / 36.,
+        }
+    }
+}
+
+impl From<Wgs84> for Lv03 {
+    /// Approximation formulas of swisstopo
+    fn from(wgs84: Wgs84) -> Self {
+        Lv95::from(wgs84).into()
+    }
+}
+
+impl From<Lv03> for Wgs84 {
+    /// Approximation formulas of swisstopo
+    fn from(lv03: Lv03) -> Self {
+        Lv95::from(lv03).into()
+    }
+}
+
+/// Swiss oblique Mercator projection on Bessel 1841 ellipsoid (rigorous
+/// formulas), shared by LV03 and LV95 with a different false origin
+mod bessel {
+    use super::FRAC_PI_4;
+
+    const A: f64 = 6_377_397.155;
+    const E2: f64 = 0.006_674_372_230_614;
+    /// Latitude of Bern (46°57'08.66")
+    const PHI0: f64 = (46. + 57. / 60. + 8.66 / 3600.) * std::f64::consts::PI / 180.;
+    /// Longitude of Bern (7°26'22.50")
+    const LAMBDA0: f64 = (7. + 26. / 60. + 22.5 / 3600.) * std::f64::consts::PI / 180.;
+
+    struct Constants {
+        e: f64,
+        r: f64,
+        alpha: f64,
+        b0: f64,
+        k: f64,
+    }
+
+    fn constants() -> Constants {
+        let e = E2.sqrt();
+        let sin0 = PHI0.sin();
+        let r = A * (1. - E2).sqrt() / (1. - E2 * sin0.powi(2));
+        let alpha = (1. + E2 / (1. - E2) * PHI0.cos().powi(4)).sqrt();
+        let b0 = (sin0 / alpha).asin();
+        let k = (FRAC_PI_4 + b0 / 2.).tan().ln() - alpha * (FRAC_PI_4 + PHI0 / 2.).tan().ln()
+            + alpha * e / 2. * ((1. + e * sin0) / (1. - e * sin0)).ln();
+        Constants { e, r, alpha, b0, k }
+    }
+
+    /// Latitude and longitude (radian) to projected coordinates relative to
+    /// Bern (east, north)
+    pub fn project(phi: f64, lambda: f64) -> [f64; 2] {
+        let Constants { e, r, alpha, b0, k } = constants();
+        let sin = phi.sin();
+        let s = alpha * (FRAC_PI_4 + phi / 2.).tan().ln()
+            - alpha * e / 2. * ((1. + e * sin) / (1. - e * sin)).ln()
+            + k;
+        let b = 2. * (s.exp().atan() - FRAC_PI_4);
+        let l = alpha * (lambda - LAMBDA0);
+        let l_bar = (l.sin() / (b0.sin() * b.tan() + b0.cos() * l.cos())).atan();
+        let b_bar = (b0.cos() * b.sin() - b0.sin() * b.cos() * l.cos()).asin();
+        [
+            r * l_bar,
+            r / 2. * ((1. + b_bar.sin()) / (1. - b_bar.sin())).ln(),
+        ]
+    }
+
+    /// Projected coordinates relative to Bern (east, north) to latitude and
+    /// longitude (radian)
+    pub fn unproject([y, x]: [f64; 2]) -> [f64; 2] {
+        let Constants { e, r, alpha, b0, k } = constants();
+        let l_bar = y / r;
+        let b_bar = 2. * ((x / r).exp().atan() - FRAC_PI_4);
+        let b = (b0.cos() * b_bar.sin() + b0.sin() * b_bar.cos() * l_bar.cos()).asin();
+        let l = (l_bar.sin() / (b0.cos() * l_bar.cos() - b0.sin() * b_bar.tan())).atan();
+        let lambda = LAMBDA0 + l / alpha;
+        let mut phi = b;
+        for _ in 0..10 {
+            let s = ((FRAC_PI_4 + b / 2.).tan().ln() - k) / alpha
+                + e * (FRAC_PI_4 + (e * phi.sin()).asin() / 2.).tan().ln();
+            phi = 2. * s.exp().atan() - 2. * FRAC_PI_4;
+        }
+        [phi, lambda]
+    }
+}
+
+/// A sub-grid of a NTv2 file, nodes are on seconds, longitude positive west
+struct SubGrid {
+    south: f64,
+    north: f64,
+    east: f64,
+    west: f64,
+    lat_inc: f64,
+    lon_inc: f64,
+    columns: usize,
+    /// Shifts of latitude and longitude by node (seconds)
+    shifts: Vec<[f32; 2]>,
+}
+impl SubGrid {
+    fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.south..=self.north).contains(&lat) && (self.east..=self.west).contains(&lon)
+    }
+
+    /// Bilinear interpolation of shift
+    fn shift(&self, lat: f64, lon: f64) -> [f64; 2] {
+        let x = (lon - self.east) / self.lon_inc;
+        let y = (lat - self.south) / self.lat_inc;
+        let rows = self.shifts.len() / self.columns;
+        let column = (x as usize).min(self.columns.saturating_sub(2));
+        let row = (y as usize).min(rows.saturating_sub(2));
+        let (dx, dy) = (x - column as f64, y - row as f64);
+        let node = |column: usize, row: usize| {
+            self.shifts
+                .get(row * self.columns + column)
+                .copied()
+                .unwrap_or_default()
+        };
+        let [s00, s10, s01, s11] = [
+            node(column, row),
+            node(column + 1, row),
+            node(column, row + 1),
+            node(column + 1, row + 1),
+        ];
+        [0, 1].map(|i| {
+            f64::from(s00[i]) * (1. - dx) * (1. - dy)
+                + f64::from(s10[i]) * dx * (1. - dy)
+                + f64::from(s01[i]) * (1. - dx) * dy
+                + f64::from(s11[i]) * dx * dy
+        })
+    }
+}
+
+/// Distortion grid of NTv2 format for precise LV03 ⇄ LV95 transformation
+///
+/// The grid contains shifts of latitude and longitude from CH1903 to
+/// CH1903+ on Bessel ellipsoid, like the CHENyx06 grid published by
+/// swisstopo (`CHENyx06a.gsb`). Coordinates are transformed with the rigorous
+/// swiss projection formulas, accuracy is about 1 millimeter.
+///
+/// ```no_run
+/// use swissdata::tools::geo::{Lv03, Ntv2Grid};
+///
+/// let grid = Ntv2Grid::from_bytes(&std::fs::read("CHENyx06a.gsb")?)?;
+/// let lv95 = grid.lv03_to_lv95(Lv03 {
+///     east: 600_000.,
+///     north: 200_000.,
+/// });
+/// println!("{lv95:?}");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Ntv2Grid {
+    grids: Vec<SubGrid>,
+}
+impl Ntv2Grid {
+    /// Parse a NTv2 file (little or big endian)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn error::Error>> {
+        let record = |index: usize| -> Result<&[u8], Box<dyn error::Error>> {
+            Ok(bytes
+                .get(index * 16..index * 16 + 16)
+                .ok_or("Unexpected end of NTv2 file")?)
+        };
+        let little = i32::from_le_bytes(record(0)?[8..12].try_into()?) == 11;
+        let int = |index: usize| -> Result<i32, Box<dyn error::Error>> {
+            let value = record(index)?[8..12].try_into()?;
+            Ok(if little {
+                i32::from_le_bytes(value)
+            } else {
+                i32::from_be_bytes(value)
+            })
+        };
+        let float = |index: usize| -> Result<f64, Box<dyn error::Error>> {
+            let value = record(index)?[8..16].try_into()?;
+            Ok(if little {
+                f64::from_le_bytes(value)
+            } else {
+                f64::from_be_bytes(value)
+            })
+        };
+        let float32 = |offset: usize| -> Result<f32, Box<dyn error::Error>> {
+            let value = bytes
+                .get(offset..offset + 4)
+                .ok_or("Unexpected end of NTv2 file")?
+                .try_into()?;
+            Ok(if little {
+                f32::from_le_bytes(value)
+            } else {
+                f32::from_be_bytes(value)
+            })
+        };
+
+        let overview_len = usize::try_from(int(0)?)?;
+        let subfiles_len = usize::try_from(int(2)?)?;
+        let mut grids = Vec::with_capacity(subfiles_len);
+        let mut index = overview_len;
+        for _ in 0..subfiles_len {
+            // SUB_NAME, PARENT, CREATED, UPDATED, then values
+            let grid_len = usize::try_from(int(index + 10)?)?;
+            let mut grid = SubGrid {
+                south: float(index + 4)?,
+                north: float(index + 5)?,
+                east: float(index + 6)?,
+                west: float(index + 7)?,
+                lat_inc: float(index + 8)?,
+                lon_inc: float(index + 9)?,
+                columns: 0,
+                shifts: Vec::with_capacity(grid_len),
+            };
+            grid.columns = ((grid.west - grid.east) / grid.lon_inc).round() as usize + 1;
+            index += 11;
+            for node in 0..grid_len {
+                let offset = (index + node) * 16;
+                grid.shifts.push([float32(offset)?, float32(offset + 4)?]);
+            }
+            index += grid_len;
+            grids.push(grid);
+        }
+        Ok(Self { grids })
+    }
+
+    /// Shift (seconds) at a CH1903 coordinate (seconds, longitude positive
+    /// west), from the finest sub-grid
+    fn shift(&self, lat: f64, lon: f64) -> Option<[f64; 2]> {
+        self.grids
+            .iter()
+            .filter(|grid| grid.contains(lat, lon))
+            .min_by(|a, b| (a.lat_inc * a.lon_inc).total_cmp(&(b.lat_inc * b.lon_inc)))
+            .map(|grid| grid.shift(lat, lon))
+    }
+
+    /// Precise LV03 to LV95 transformation, `None` if outside of the grid
+    pub fn lv03_to_lv95(&self, lv03: Lv03) -> Option<Lv95> {
+        let [phi, lambda] = bessel::unproject([lv03.east - 600_000., lv03.north - 200_000.]);
+        let (lat, lon) = (phi.to_degrees() * 3600., -lambda.to_degrees() * 3600.);
+        let [d_lat, d_lon] = self.shift(lat, lon)?;
+        let [east, north] = bessel::project(
+            ((lat + d_lat) / 3600.).to_radians(),
+            (-(lon + d_lon) / 3600.).to_radians(),
+        );
+        Some(Lv95 {
+            east: east + 2_600_000.,
+            north: north + 1_200_000.,
+        })
+    }
+
+    /// Precise LV95 to LV03 transformation, `None` if outside of the grid
+    pub fn lv95_to_lv03(&self, lv95: Lv95) -> Option<Lv03> {
+        let [phi, lambda] = bessel::unproject([lv95.east - 2_600_000., lv95.north - 1_200_000.]);
+        let (target_lat, target_lon) = (phi.to_degrees() * 3600., -lambda.to_degrees() * 3600.);
+        // shifts are defined on CH1903, iterate for find the source
+        let (mut lat, mut lon) = (target_lat, target_lon);
+        for _ in 0..4 {
+            let [d_lat, d_lon] = self.shift(lat, lon)?;
+            lat = target_lat - d_lat;
+            lon = target_lon - d_lon;
+        }
+        let [east, north] =
+            bessel::project((lat / 3600.).to_radians(), (-lon / 3600.).to_radians());
+        Some(Lv03 {
+            east: east + 600_000.,
+            north: north + 200_000.,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// NTv2 file with one grid covering Switzerland (1 degree nodes) and a
+    /// constant shift (seconds, longitude positive west)
+    fn ntv2(little: bool, [d_lat, d_lon]: [f32; 2]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut record = |name: &str, value: &[u8]| {
+            let mut record = [0; 16];
+            record[..name.len()].copy_from_slice(name.as_bytes());
+            record[8..8 + value.len()].copy_from_slice(value);
+            bytes.extend(record);
+        };
+        let int = |value: i32| {
+            if little {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            }
+        };
+        let float = |value: f64| {
+            if little {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            }
+        };
+        let (columns, rows) = (7, 4);
+        record("NUM_OREC", &int(11));
+        record("NUM_SREC", &int(11));
+        record("NUM_FILE", &int(1));
+        for name in ["GS_TYPE", "VERSION", "SYSTEM_F", "SYSTEM_T"] {
+            record(name, b"");
+        }
+        for name in ["MAJOR_F", "MINOR_F", "MAJOR_T", "MINOR_T"] {
+            record(name, &float(0.));
+        }
+        for name in ["SUB_NAME", "PARENT", "CREATED", "UPDATED"] {
+            record(name, b"");
+        }
+        record("S_LAT", &float(45. * 3600.));
+        record("N_LAT", &float(48. * 3600.));
+        record("E_LONG", &float(-11. * 3600.));
+        record("W_LONG", &float(-5. * 3600.));
+        record("LAT_INC", &float(3600.));
+        record("LONG_INC", &float(3600.));
+        record("GS_COUNT", &int(columns * rows));
+        for _ in 0..columns * rows {
+            let mut node = Vec::new();
+            for value in [d_lat, d_lon, 0., 0.] {
+                node.extend(if little {
+                    value.to_le_bytes()
+                } else {
+                    value.to_be_bytes()
+                });
+            }
+            bytes.extend(node);
+        }
+        bytes
+    }
+
+    const POINTS: [Lv03; 3] = [
+        Lv03 {
+            east: 600_000.,
+            north: 200_000.,
+        },
+        Lv03 {
+            east: 700_000.,
+            north: 100_000.,
+        },
+        Lv03 {
+            east: 500_000.,
+            north: 280_000.,
+        },
+    ];
+
+    #[test]
+    fn bessel_projection() {
+        // origin is Bern (46°57'08.66", 7°26'22.50" on Bessel ellipsoid)
+        let phi0 = (46. + 57. / 60. + 8.66 / 3600_f64).to_radians();
+        let lambda0 = (7. + 26. / 60. + 22.5 / 3600_f64).to_radians();
+        let bern = bessel::project(phi0, lambda0);
+        assert!(bern.iter().all(|value| value.abs() < 1e-6));
+        // one second of latitude is about 30.9 m
+        let [east, north] = bessel::project(phi0 + (1. / 3600_f64).to_radians(), lambda0);
+        assert!(east.abs() < 1e-6);
+        assert!((30.8..31.0).contains(&north), "north {north}");
+
+        for lv03 in POINTS {
+            let [phi, lambda] = bessel::unproject([lv03.east - 600_000., lv03.north - 200_000.]);
+            let [east, north] = bessel::project(phi, lambda);
+            assert!((east + 600_000. - lv03.east).abs() < 1e-6);
+            assert!((north + 200_000. - lv03.north).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn grid_without_shift() {
+        let grid = Ntv2Grid::from_bytes(&ntv2(true, [0., 0.])).unwrap();
+        for lv03 in POINTS {
+            let lv95 = grid.lv03_to_lv95(lv03).unwrap();
+            assert!((lv95.east - lv03.east - 2_000_000.).abs() < 1e-3);
+            assert!((lv95.north - lv03.north - 1_000_000.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn grid_constant_shift() {
+        // 0.1" north (about 3.09 m) and 0.05" east (about 1.06 m)
+        let shift = [0.1, -0.05];
+        let little = Ntv2Grid::from_bytes(&ntv2(true, shift)).unwrap();
+        let big = Ntv2Grid::from_bytes(&ntv2(false, shift)).unwrap();
+        for lv03 in POINTS {
+            let lv95 = little.lv03_to_lv95(lv03).unwrap();
+            assert_eq!(big.lv03_to_lv95(lv03), Some(lv95));
+            let east = lv95.east - lv03.east - 2_000_000.;
+            let north = lv95.north - lv03.north - 1_000_000.;
+            assert!((1.0..1.1).contains(&east), "east shift {east}");
+            assert!((3.0..3.2).contains(&north), "north shift {north}");
+
+            let back = little.lv95_to_lv03(lv95).unwrap();
+            assert!((back.east - lv03.east).abs() < 1e-3);
+            assert!((back.north - lv03.north).abs() < 1e-3);
+        }
+
+        let outside = Lv03 {
+            east: 0.,
+            north: 0.,
+        };
+        assert_eq!(little.lv03_to_lv95(outside), None);
+    }
+
+    #[test]
+    fn truncated_grid() {
+        let bytes = ntv2(true, [0., 0.]);
+        // in the shifts of last node
+        assert!(Ntv2Grid::from_bytes(&bytes[..bytes.len() - 9]).is_err());
+        assert!(Ntv2Grid::from_bytes(&bytes[..100]).is_err());
+    }
+}
@@ -1 +1,4 @@
 pub mod serde;
+pub mod shapefile;
+#[cfg(test)]
+pub mod testing;
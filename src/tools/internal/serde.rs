@@ -50,3 +50,44 @@ pub mod date_dd_mm_yyyyy_dotted {
         Date::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
     }
 }
+pub mod option_system_time_rfc3339 {
+    use std::time::SystemTime;
+
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    /// Time as text, or as serialized by `serde` (previous format)
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Time {
+        Text(String),
+        Struct(SystemTime),
+    }
+
+    pub fn serialize<S>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(time) => {
+                let s = DateTime::<Utc>::from(*time).to_rfc3339_opts(SecondsFormat::AutoSi, true);
+                serializer.serialize_some(&s)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let time = Option::<Time>::deserialize(deserializer)?;
+        time.map(|time| match time {
+            Time::Text(s) => DateTime::parse_from_rfc3339(&s)
+                .map(SystemTime::from)
+                .map_err(serde::de::Error::custom),
+            Time::Struct(time) => Ok(time),
+        })
+        .transpose()
+    }
+}
@@ -0,0 +1,196 @@
+//! Minimal reader for ESRI shapefiles: polygons of `.shp` and attributes of
+//! `.dbf`
+//!
+//! Only polygon shapes (types 5, 15 and 25, Z and M values are ignored) and
+//! null shapes are supported.
+
+use std::collections::HashMap;
+use std::error;
+
+use encoding_rs::Encoding;
+
+/// Rings of a polygon shape, outer rings and holes are not distinguished
+pub type Rings = Vec<Vec<[f64; 2]>>;
+/// Attributes of a record by field name
+pub type Record = HashMap<String, String>;
+
+const SHP_FILE_CODE: i32 = 9994;
+const SHP_HEADER_LEN: usize = 100;
+const SHAPE_NULL: i32 = 0;
+const SHAPE_POLYGONS: [i32; 3] = [5, 15, 25];
+
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], Box<dyn error::Error>> {
+    Ok(data
+        .get(offset..offset + N)
+        .ok_or("Unexpected end of shapefile")?
+        .try_into()?)
+}
+
+fn i32_be(data: &[u8], offset: usize) -> Result<i32, Box<dyn error::Error>> {
+    Ok(i32::from_be_bytes(bytes(data, offset)?))
+}
+
+fn i32_le(data: &[u8], offset: usize) -> Result<i32, Box<dyn error::Error>> {
+    Ok(i32::from_le_bytes(bytes(data, offset)?))
+}
+
+fn f64_le(data: &[u8], offset: usize) -> Result<f64, Box<dyn error::Error>> {
+    Ok(f64::from_le_bytes(bytes(data, offset)?))
+}
+
+fn usize_le(data: &[u8], offset: usize) -> Result<usize, Box<dyn error::Error>> {
+    Ok(usize::try_from(i32_le(data, offset)?)?)
+}
+
+/// Read all shapes of a `.shp` file, a null shape has no ring
+pub fn read_polygons(shp: &[u8]) -> Result<Vec<Rings>, Box<dyn error::Error>> {
+    if i32_be(shp, 0)? != SHP_FILE_CODE {
+        return Err("Invalid shapefile header".into());
+    }
+    let mut shapes = Vec::new();
+    let mut offset = SHP_HEADER_LEN;
+    while offset < shp.len() {
+        let length = usize::try_from(i32_be(shp, offset + 4)?)? * 2;
+        let content = shp
+            .get(offset + 8..offset + 8 + length)
+            .ok_or("Unexpected end of shapefile")?;
+        shapes.push(read_polygon(content)?);
+        offset += 8 + length;
+    }
+    Ok(shapes)
+}
+
+fn read_polygon(content: &[u8]) -> Result<Rings, Box<dyn error::Error>> {
+    let shape_type = i32_le(content, 0)?;
+    if shape_type == SHAPE_NULL {
+        return Ok(Vec::new());
+    }
+    if !SHAPE_POLYGONS.contains(&shape_type) {
+        return Err(format!("Unsupported shape type {shape_type}").into());
+    }
+    // shape type (4) + bounding box (32)
+    let parts_len = usize_le(content, 36)?;
+    let points_len = usize_le(content, 40)?;
+    let parts = (0..parts_len)
+        .map(|i| usize_le(content, 44 + 4 * i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let points_offset = 44 + 4 * parts_len;
+    let point = |i: usize| -> Result<[f64; 2], Box<dyn error::Error>> {
+        let offset = points_offset + 16 * i;
+        Ok([f64_le(content, offset)?, f64_le(content, offset + 8)?])
+    };
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = parts.get(i + 1).copied().unwrap_or(points_len);
+            (start..end).map(point).collect()
+        })
+        .collect()
+}
+
+/// Read all records of a `.dbf` file, values are trimmed
+///
+/// Deleted records are `None`, so records keep the order of shapes.
+pub fn read_dbf(
+    dbf: &[u8],
+    encoding: &'static Encoding,
+) -> Result<Vec<Option<Record>>, Box<dyn error::Error>> {
+    let records_len = u32::from_le_bytes(bytes(dbf, 4)?);
+    let header_len = usize::from(u16::from_le_bytes(bytes(dbf, 8)?));
+    let record_len = usize::from(u16::from_le_bytes(bytes(dbf, 10)?));
+
+    let mut fields = Vec::new();
+    let mut offset = 32;
+    while *dbf.get(offset).ok_or("Unexpected end of dbf file")? != 0x0D {
+        let descriptor: [u8; 32] = bytes(dbf, offset)?;
+        let name = descriptor[..11].split(|b| *b == 0).next().unwrap_or(&[]);
+        fields.push((
+            String::from_utf8_lossy(name).into_owned(),
+            usize::from(descriptor[16]),
+        ));
+        offset += 32;
+    }
+
+    let mut records = Vec::new();
+    for i in 0..records_len as usize {
+        let offset = header_len + i * record_len;
+        let record = dbf
+            .get(offset..offset + record_len)
+            .ok_or("Unexpected end of dbf file")?;
+        if record.first() == Some(&b'*') {
+            records.push(None);
+            continue;
+        }
+        let mut values = HashMap::new();
+        let mut start = 1;
+        for (name, len) in &fields {
+            let raw = record.get(start..start + len).ok_or("Invalid dbf record")?;
+            let (value, _) = encoding.decode_without_bom_handling(raw);
+            values.insert(name.clone(), value.trim().to_string());
+            start += len;
+        }
+        records.push(Some(values));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{UTF_8, WINDOWS_1252};
+
+    use super::*;
+    use crate::tools::internal::testing::{dbf, shp};
+
+    fn square(min: f64, max: f64) -> Vec<[f64; 2]> {
+        vec![[min, min], [min, max], [max, max], [max, min], [min, min]]
+    }
+
+    #[test]
+    fn polygon_with_hole() {
+        let rings = vec![square(0., 10.), square(2., 4.)];
+        let shapes = read_polygons(&shp(&[Some(rings.clone())])).unwrap();
+        assert_eq!(shapes, [rings]);
+    }
+
+    #[test]
+    fn null_shape() {
+        let shapes = read_polygons(&shp(&[None, Some(vec![square(0., 1.)])])).unwrap();
+        assert_eq!(shapes, [vec![], vec![square(0., 1.)]]);
+    }
+
+    #[test]
+    fn invalid_shapes() {
+        let mut data = shp(&[Some(vec![square(0., 1.)])]);
+        assert!(read_polygons(&data[..data.len() - 1]).is_err());
+        // polyline
+        data[108..112].copy_from_slice(&3i32.to_le_bytes());
+        assert!(read_polygons(&data).is_err());
+        data[..4].copy_from_slice(&0i32.to_be_bytes());
+        assert!(read_polygons(&data).is_err());
+    }
+
+    #[test]
+    fn records() {
+        let data = dbf(
+            &[("GMDNR", 4), ("GMDNAME", 12)],
+            &[
+                Some(vec![b"6458", b"Neuch\xe2tel"]),
+                None,
+                Some(vec![b"261", b"Z\xfcrich"]),
+            ],
+        );
+        let records = read_dbf(&data, WINDOWS_1252).unwrap();
+        assert_eq!(records.len(), 3);
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(first["GMDNR"], "6458");
+        assert_eq!(first["GMDNAME"], "Neuchâtel");
+        assert!(records[1].is_none());
+        assert_eq!(records[2].as_ref().unwrap()["GMDNAME"], "Zürich");
+
+        let data = dbf(&[("GMDNAME", 12)], &[Some(vec!["Zürich".as_bytes()])]);
+        let records = read_dbf(&data, UTF_8).unwrap();
+        assert_eq!(records[0].as_ref().unwrap()["GMDNAME"], "Zürich");
+        assert!(read_dbf(&data[..data.len() - 4], UTF_8).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Helpers shared by tests
+
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::fso::communes::TXT_FSO_ID;
+
+/// Url of content served in tests
+pub const URL: &str = "https://example.org/data.txt";
+
+/// Counter of temporary directories of this process
+static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Empty temporary directory, removed on drop
+pub struct TempDir(PathBuf);
+impl TempDir {
+    /// Path of directory
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Create an empty temporary directory, unique in all tests
+pub fn temp_dir() -> TempDir {
+    let id = DIRS.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("rust-swissdata-test-{}-{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    TempDir(dir)
+}
+
+/// Set the modification time of a file
+pub fn set_modified(path: &Path, time: SystemTime) {
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+/// Archive of communes with one canton, district and municipality
+pub fn communes_zip() -> Vec<u8> {
+    let files = [
+        ("KT", "1\tZH\tZuerich\t12.09.1848\r\n"),
+        (
+            "BEZ",
+            "10053\t1\t101\tBezirk Affoltern\tAffoltern\t15\t1000\t20\t\
+             01.01.1960\t\t\t\t01.01.1960\r\n",
+        ),
+        (
+            "GDE",
+            "11742\t10053\tZH\t1\tAeugst am Albis\tAeugst am Albis\t11\t1\t\
+             1000\t20\t01.01.1960\t\t\t\t01.01.1960\r\n",
+        ),
+    ];
+    zip(files.map(|(kind, content)| {
+        (
+            format!("{TXT_FSO_ID}/1.2/20240101_GDEHist_{kind}.txt"),
+            content.as_bytes().to_vec(),
+        )
+    }))
+}
+
+/// Zip archive with files (name and content)
+pub fn zip<I: IntoIterator<Item = (String, Vec<u8>)>>(files: I) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(name, FileOptions::default()).unwrap();
+        zip.write_all(&content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Content of a `.shp` file with polygon shapes, `None` is a null shape
+pub fn shp(shapes: &[Option<Vec<Vec<[f64; 2]>>>]) -> Vec<u8> {
+    let mut data = vec![0; 100];
+    data[..4].copy_from_slice(&9994i32.to_be_bytes());
+    data[28..32].copy_from_slice(&1000i32.to_le_bytes());
+    for (i, shape) in shapes.iter().enumerate() {
+        let mut content = Vec::new();
+        match shape {
+            None => content.extend(0i32.to_le_bytes()),
+            Some(rings) => {
+                // bounding box isn't read
+                content.extend(5i32.to_le_bytes());
+                content.extend([0; 32]);
+                let points: usize = rings.iter().map(Vec::len).sum();
+                content.extend((rings.len() as i32).to_le_bytes());
+                content.extend((points as i32).to_le_bytes());
+                let mut start = 0;
+                for ring in rings {
+                    content.extend((start as i32).to_le_bytes());
+                    start += ring.len();
+                }
+                for [x, y] in rings.iter().flatten() {
+                    content.extend(x.to_le_bytes());
+                    content.extend(y.to_le_bytes());
+                }
+            }
+        }
+        data.extend((i as i32 + 1).to_be_bytes());
+        data.extend((content.len() as i32 / 2).to_be_bytes());
+        data.extend(content);
+    }
+    let len = (data.len() / 2) as i32;
+    data[24..28].copy_from_slice(&len.to_be_bytes());
+    data
+}
+
+/// Content of a `.dbf` file with character fields (name and length), `None`
+/// is a deleted record
+pub fn dbf(fields: &[(&str, usize)], records: &[Option<Vec<&[u8]>>]) -> Vec<u8> {
+    let header_len = 32 + 32 * fields.len() + 1;
+    let record_len = 1 + fields.iter().map(|(_, len)| len).sum::<usize>();
+    let mut data = vec![0; 32];
+    data[0] = 3;
+    data[4..8].copy_from_slice(&(records.len() as u32).to_le_bytes());
+    data[8..10].copy_from_slice(&(header_len as u16).to_le_bytes());
+    data[10..12].copy_from_slice(&(record_len as u16).to_le_bytes());
+    for (name, len) in fields {
+        let mut descriptor = [0; 32];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[11] = b'C';
+        descriptor[16] = *len as u8;
+        data.extend(descriptor);
+    }
+    data.push(0x0D);
+    for record in records {
+        match record {
+            None => data.extend(vec![b'*'; record_len]),
+            Some(values) => {
+                data.push(b' ');
+                for (value, (_, len)) in values.iter().zip(fields) {
+                    data.extend(*value);
+                    data.extend(vec![b' '; len - value.len()]);
+                }
+            }
+        }
+    }
+    data.push(0x1A);
+    data
+}
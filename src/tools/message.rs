@@ -26,6 +26,11 @@ impl<T> Translated<T> {
     pub fn get_or_default<S: AsRef<str>>(&self, key: S) -> &T {
         self.get(key).unwrap_or_else(|| self.default())
     }
+
+    /// Iterate on languages and their T
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.contents.iter().map(|(lang, t)| (lang.as_str(), t))
+    }
 }
 impl<T> Default for Translated<T>
 where
@@ -64,6 +64,53 @@ citations: {citations:?}
 [terms]: {terms_url}"
         )
     }
+
+    /// Flatten meta information on key-value pairs (for file metadata)
+    ///
+    /// Keys are prefixed by `swissdata.` and suffixed by the language for
+    /// translated values (like `swissdata.editor.fr`).
+    pub fn to_key_values(&self) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        for (lang, name) in self.name.iter() {
+            pairs.push((format!("swissdata.name.{lang}"), name.to_string()));
+        }
+        for (key, links) in [
+            ("editor", &self.editor),
+            ("copyright", &self.copyright),
+            ("terms", &self.terms),
+        ] {
+            for (lang, link) in links.iter() {
+                pairs.push((format!("swissdata.{key}.{lang}"), link.name().to_string()));
+                pairs.push((
+                    format!("swissdata.{key}_url.{lang}"),
+                    link.url().to_string(),
+                ));
+            }
+        }
+        let Terms {
+            free_commercial_use: co,
+            free_noncommercial_use: nc,
+            citation_mandatory: cite,
+        } = &self.terms_automatic;
+        pairs.push(("swissdata.terms.free_commercial_use".into(), co.to_string()));
+        pairs.push((
+            "swissdata.terms.free_noncommercial_use".into(),
+            nc.to_string(),
+        ));
+        pairs.push((
+            "swissdata.terms.citation_mandatory".into(),
+            cite.to_string(),
+        ));
+        for (lang, citation) in self.citations.iter() {
+            if let Some(bibtex) = citation.bibtex() {
+                pairs.push((format!("swissdata.citation.bibtex.{lang}"), bibtex));
+            }
+            if let Some(ris) = citation.ris() {
+                pairs.push((format!("swissdata.citation.ris.{lang}"), ris));
+            }
+        }
+        pairs
+    }
 }
 impl<S> fmt::Debug for Meta<S>
 where
@@ -2,8 +2,10 @@
 
 pub mod dataset;
 pub mod downloader;
+pub mod geo;
 pub(crate) mod internal;
 pub mod message;
 pub mod meta;
+pub mod validity;
 
 pub use downloader::Downloader;
@@ -0,0 +1,257 @@
+//! Validity interval of a record and index for search records valid during a
+//! period
+
+use std::cmp::Ordering;
+
+use crate::Date;
+
+/// Period of validity, bounds are inclusive
+///
+/// `to` is the last day of validity, [None] if still valid.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Validity {
+    /// First day of validity
+    pub from: Date,
+    /// Last day of validity (None if still valid)
+    pub to: Option<Date>,
+}
+impl Validity {
+    /// Validity from `from` to `to` (inclusive)
+    pub fn new(from: Date, to: Option<Date>) -> Self {
+        Self { from, to }
+    }
+
+    /// Validity of only one day
+    pub fn day(date: Date) -> Self {
+        Self::new(date, Some(date))
+    }
+
+    /// Indicator if this validity has no end
+    pub fn is_open(&self) -> bool {
+        self.to.is_none()
+    }
+
+    /// Indicator if this validity contains no day (`to` before `from`)
+    pub fn is_empty(&self) -> bool {
+        matches!(self.to, Some(to) if to < self.from)
+    }
+
+    /// Indicator if `date` is on this validity
+    pub fn contains(&self, date: &Date) -> bool {
+        &self.from <= date && self.to.is_none_or(|to| date <= &to)
+    }
+
+    /// Indicator if the two validity have at least one day in common
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Period in common of two validity
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let from = self.from.max(other.from);
+        let to = match (self.to, other.to) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let validity = Self::new(from, to);
+        (!validity.is_empty()).then_some(validity)
+    }
+}
+
+/// Compare end of validity, None (still valid) is after all date
+fn cmp_to(a: &Option<Date>, b: &Option<Date>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    }
+}
+
+/// Index of records by [Validity], for search records valid during a period
+///
+/// This is a static augmented interval tree: entries are sorted by start of
+/// validity and each node of the implicit balanced tree keeps the maximum end
+/// of validity of its subtree. A search costs `O(log n + k)` for `k` results.
+///
+/// ```no_run
+/// use swissdata::fso::communes;
+/// use swissdata::tools::dataset::Datastore;
+/// use swissdata::tools::validity::Validity;
+/// use swissdata::Date;
+///
+/// let ds = communes::datastore().load(&reqwest::blocking::Client::new())?;
+/// let index = ds.municipalities.validity_index()?;
+/// let period = Validity::new(
+///     Date::from_ymd_opt(1900, 1, 1).unwrap(),
+///     Date::from_ymd_opt(1950, 12, 31),
+/// );
+/// println!("{} municipalities", index.during(&period).count());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct ValidityIndex<T> {
+    entries: Vec<(Validity, T)>,
+    max_to: Vec<Option<Date>>,
+}
+impl<T> ValidityIndex<T> {
+    /// Build index from records and their validity
+    pub fn new<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Validity, T)>,
+    {
+        let mut entries: Vec<_> = iter.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.from.cmp(&b.from).then(cmp_to(&a.to, &b.to)));
+        let mut max_to = vec![None; entries.len()];
+        Self::build(&entries, &mut max_to, 0, entries.len());
+        Self { entries, max_to }
+    }
+
+    fn build(
+        entries: &[(Validity, T)],
+        max_to: &mut [Option<Date>],
+        lo: usize,
+        hi: usize,
+    ) -> Option<Option<Date>> {
+        if lo >= hi {
+            return None;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let mut max = entries[mid].0.to;
+        for child in [
+            Self::build(entries, max_to, lo, mid),
+            Self::build(entries, max_to, mid + 1, hi),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if cmp_to(&child, &max) == Ordering::Greater {
+                max = child;
+            }
+        }
+        max_to[mid] = max;
+        Some(max)
+    }
+
+    /// Number of records in index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Indicator if index contains no record
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate on all records (sorted by start of validity)
+    pub fn iter(&self) -> impl Iterator<Item = (&Validity, &T)> {
+        self.entries.iter().map(|(v, t)| (v, t))
+    }
+
+    /// Records valid at least one day during `period`
+    pub fn during(&self, period: &Validity) -> impl Iterator<Item = (&Validity, &T)> {
+        let mut found = Vec::new();
+        if !period.is_empty() {
+            self.search(period, 0, self.entries.len(), &mut found);
+        }
+        found.into_iter().map(|i| {
+            let (v, t) = &self.entries[i];
+            (v, t)
+        })
+    }
+
+    /// Records valid at `date`
+    pub fn at(&self, date: Date) -> impl Iterator<Item = (&Validity, &T)> {
+        self.during(&Validity::day(date))
+    }
+
+    fn search(&self, period: &Validity, lo: usize, hi: usize, found: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        // no record of subtree ends after start of period
+        if matches!(self.max_to[mid], Some(to) if to < period.from) {
+            return;
+        }
+        self.search(period, lo, mid, found);
+        let validity = &self.entries[mid].0;
+        // records of right subtree start after this one
+        if matches!(period.to, Some(to) if to < validity.from) {
+            return;
+        }
+        if validity.overlaps(period) {
+            found.push(mid);
+        }
+        self.search(period, mid + 1, hi, found);
+    }
+}
+impl<T> FromIterator<(Validity, T)> for ValidityIndex<T> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Validity, T)>,
+    {
+        Self::new(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo-random validities (linear congruential generator), with open
+    /// and empty validities
+    fn validities(len: usize) -> Vec<Validity> {
+        let mut state: u64 = 42;
+        let mut next = |max: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % max
+        };
+        let origin = Date::from_ymd_opt(1960, 1, 1).unwrap();
+        (0..len)
+            .map(|_| {
+                let from = origin + chrono::Days::new(next(3000));
+                let to = match next(10) {
+                    0 => None,
+                    1 => from.pred_opt(),
+                    _ => Some(from + chrono::Days::new(next(500))),
+                };
+                Validity::new(from, to)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn search_matches_brute_force() {
+        let entries = validities(500);
+        let index: ValidityIndex<usize> = entries.iter().copied().zip(0..).collect();
+        assert_eq!(index.len(), entries.len());
+        let mut total = 0;
+        for period in validities(300) {
+            let mut found: Vec<_> = index.during(&period).map(|(_, i)| *i).collect();
+            found.sort_unstable();
+            let expected: Vec<_> = (0..entries.len())
+                .filter(|i| !period.is_empty() && entries[*i].overlaps(&period))
+                .collect();
+            assert_eq!(found, expected, "during {period:?}");
+            total += found.len();
+        }
+        assert!(total > 0);
+        let date = Date::from_ymd_opt(1965, 6, 1).unwrap();
+        let mut found: Vec<_> = index.at(date).map(|(_, i)| *i).collect();
+        found.sort_unstable();
+        let expected: Vec<_> = (0..entries.len())
+            .filter(|i| entries[*i].contains(&date))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn empty_index() {
+        let index: ValidityIndex<()> = ValidityIndex::new([]);
+        assert!(index.is_empty());
+        let period = validities(1)[0];
+        assert_eq!(index.during(&period).count(), 0);
+    }
+}